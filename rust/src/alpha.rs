@@ -0,0 +1,262 @@
+use crate::errors::CompressorError;
+use image::DynamicImage;
+use std::collections::VecDeque;
+
+/// 完全透明像素的 RGB 处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlphaCleanup {
+    /// 保留原值
+    None,
+    /// 置为 0，适合无损输出
+    Zero,
+    /// 用最近的可见像素颜色填充，有损编码与缩放时边缘不会混入杂色
+    Bleed,
+}
+
+pub fn parse_alpha_cleanup(name: &str) -> Result<AlphaCleanup, CompressorError> {
+    match name {
+        "none" => Ok(AlphaCleanup::None),
+        "zero" => Ok(AlphaCleanup::Zero),
+        "bleed" => Ok(AlphaCleanup::Bleed),
+        _ => Err(CompressorError::InvalidOption(format!(
+            "Unknown alpha cleanup: {name}"
+        ))),
+    }
+}
+
+/// 清理透明像素的颜色；alpha 全为不透明时直接去掉 alpha 通道
+pub fn clean(image: DynamicImage, mode: AlphaCleanup) -> DynamicImage {
+    if mode == AlphaCleanup::None || !image.color().has_alpha() {
+        return image;
+    }
+    if !crate::utils::has_transparency(&image) {
+        return drop_alpha(image);
+    }
+
+    let mut rgba = image.into_rgba8();
+    match mode {
+        AlphaCleanup::Zero => {
+            for pixel in rgba.pixels_mut().filter(|pixel| pixel[3] == 0) {
+                pixel.0 = [0, 0, 0, 0];
+            }
+        }
+        AlphaCleanup::Bleed => bleed(&mut rgba),
+        AlphaCleanup::None => {}
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
+fn drop_alpha(image: DynamicImage) -> DynamicImage {
+    match image {
+        DynamicImage::ImageLumaA8(_) => DynamicImage::ImageLuma8(image.into_luma8()),
+        DynamicImage::ImageLumaA16(_) => DynamicImage::ImageLuma16(image.into_luma16()),
+        DynamicImage::ImageRgba16(_) => DynamicImage::ImageRgb16(image.into_rgb16()),
+        DynamicImage::ImageRgba32F(_) => DynamicImage::ImageRgb32F(image.into_rgb32f()),
+        image => DynamicImage::ImageRgb8(image.into_rgb8()),
+    }
+}
+
+/// 从可见像素向外逐层扩散（广度优先），每个透明像素取最先到达的邻居颜色，alpha 保持为 0
+fn bleed(rgba: &mut image::RgbaImage) {
+    let (width, height) = (rgba.width() as usize, rgba.height() as usize);
+    let pixels: &mut [u8] = rgba.as_mut();
+    let mut filled: Vec<bool> = pixels.chunks_exact(4).map(|pixel| pixel[3] > 0).collect();
+    let mut queue: VecDeque<usize> = (0..filled.len()).filter(|&i| filled[i]).collect();
+
+    while let Some(i) = queue.pop_front() {
+        let (x, y) = (i % width, i / width);
+        let neighbors = [
+            (x > 0).then(|| i - 1),
+            (x + 1 < width).then(|| i + 1),
+            (y > 0).then(|| i - width),
+            (y + 1 < height).then(|| i + width),
+        ];
+        for next in neighbors.into_iter().flatten() {
+            if !filled[next] {
+                filled[next] = true;
+                pixels.copy_within(i * 4..i * 4 + 3, next * 4);
+                queue.push_back(next);
+            }
+        }
+    }
+}
+
+/// 转为不支持透明的格式（JPEG）时垫在下面的背景
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Background {
+    Color([u8; 3]),
+    /// 8×8 的白 / 浅灰棋盘格，用于预览
+    Checker,
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Color([255, 255, 255])
+    }
+}
+
+/// "#rrggbb"、"#rgb"（# 可省略）或 "checker"
+pub fn parse_background(value: &str) -> Result<Background, CompressorError> {
+    if value == "checker" {
+        return Ok(Background::Checker);
+    }
+    let invalid = || CompressorError::InvalidOption(format!("Invalid background color: {value}"));
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| invalid());
+    match hex.len() {
+        6 => Ok(Background::Color([
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        ])),
+        3 => Ok(Background::Color([
+            channel(&hex[0..1])? * 17,
+            channel(&hex[1..2])? * 17,
+            channel(&hex[2..3])? * 17,
+        ])),
+        _ => Err(invalid()),
+    }
+}
+
+impl Background {
+    fn color_at(self, x: u32, y: u32) -> [u8; 3] {
+        match self {
+            Background::Color(color) => color,
+            Background::Checker if (x / 8 + y / 8).is_multiple_of(2) => [255, 255, 255],
+            Background::Checker => [204, 204, 204],
+        }
+    }
+
+    /// 把 (x, y) 处的 RGBA 像素合成到背景上
+    pub fn blend(self, pixel: &[u8], x: u32, y: u32) -> [u8; 3] {
+        let alpha = u32::from(pixel[3]);
+        let background = self.color_at(x, y);
+        std::array::from_fn(|channel| {
+            ((u32::from(pixel[channel]) * alpha
+                + u32::from(background[channel]) * (255 - alpha)
+                + 127)
+                / 255) as u8
+        })
+    }
+}
+
+/// 去掉透明通道：有 alpha 时合成到背景上，否则与 utils::rgb8 相同
+pub fn flatten(
+    image: &DynamicImage,
+    background: Background,
+) -> std::borrow::Cow<'_, image::RgbImage> {
+    if !image.color().has_alpha() {
+        return crate::utils::rgb8(image);
+    }
+    let rgba = crate::utils::rgba8(image);
+    let rgb = image::RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        image::Rgb(background.blend(&rgba.get_pixel(x, y).0, x, y))
+    });
+    crate::utils::recycle_image(rgba);
+    std::borrow::Cow::Owned(rgb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bleed_fills_transparent_pixels_from_nearest_opaque_color() {
+        // 透明像素带有残留颜色，左右两端各有一个可见像素
+        let mut image = image::RgbaImage::from_pixel(6, 3, image::Rgba([9, 99, 199, 0]));
+        image.put_pixel(0, 1, image::Rgba([255, 0, 0, 255]));
+        image.put_pixel(5, 1, image::Rgba([0, 0, 255, 128]));
+
+        let cleaned =
+            clean(DynamicImage::ImageRgba8(image.clone()), AlphaCleanup::Bleed).into_rgba8();
+        for (x, y, pixel) in cleaned.enumerate_pixels() {
+            let original = image.get_pixel(x, y);
+            assert_eq!(pixel[3], original[3], "alpha at ({x}, {y})");
+            if original[3] > 0 {
+                assert_eq!(pixel, original);
+            } else {
+                let nearest = if x < 3 { [255, 0, 0] } else { [0, 0, 255] };
+                assert_eq!(pixel.0[..3], nearest, "color at ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn opaque_alpha_is_dropped() {
+        let image = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            2,
+            2,
+            image::Rgba([10, 20, 30, 255]),
+        ));
+        for mode in [AlphaCleanup::Zero, AlphaCleanup::Bleed] {
+            assert_eq!(clean(image.clone(), mode).color(), image::ColorType::Rgb8);
+        }
+        assert_eq!(
+            clean(image, AlphaCleanup::None).color(),
+            image::ColorType::Rgba8
+        );
+    }
+
+    #[test]
+    fn background_accepts_hex_and_checker() {
+        let cases = [
+            ("#336699", Background::Color([0x33, 0x66, 0x99])),
+            ("336699", Background::Color([0x33, 0x66, 0x99])),
+            ("#AbCdEf", Background::Color([0xab, 0xcd, 0xef])),
+            ("#369", Background::Color([0x33, 0x66, 0x99])),
+            ("fff", Background::Color([255, 255, 255])),
+            ("checker", Background::Checker),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(parse_background(value).unwrap(), expected, "{value}");
+        }
+
+        for value in [
+            "", "#", "#12", "#1234", "#1234567", "#ggg", "#+12345", "white", "Checker",
+        ] {
+            assert!(
+                matches!(
+                    parse_background(value),
+                    Err(CompressorError::InvalidOption(_))
+                ),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn blend_composites_onto_background() {
+        let half_red = [255, 0, 0, 128];
+        assert_eq!(
+            Background::default().blend(&half_red, 0, 0),
+            [255, 127, 127]
+        );
+        assert_eq!(
+            Background::Color([0, 0, 0]).blend(&half_red, 0, 0),
+            [128, 0, 0]
+        );
+        assert_eq!(
+            Background::default().blend(&[1, 2, 3, 255], 0, 0),
+            [1, 2, 3]
+        );
+
+        // 棋盘格按 8 像素交替
+        let transparent = [0, 0, 0, 0];
+        assert_eq!(
+            Background::Checker.blend(&transparent, 7, 7),
+            [255, 255, 255]
+        );
+        assert_eq!(
+            Background::Checker.blend(&transparent, 8, 0),
+            [204, 204, 204]
+        );
+        assert_eq!(
+            Background::Checker.blend(&transparent, 8, 8),
+            [255, 255, 255]
+        );
+        assert_eq!(Background::Checker.blend(&half_red, 0, 8), [230, 102, 102]);
+    }
+}
@@ -0,0 +1,181 @@
+#[cfg(feature = "webp")]
+use crate::alpha::AlphaCleanup;
+use crate::errors::CompressorError;
+use crate::resize::{apply_resize, ResizeOptions};
+use image::{AnimationDecoder, ImageDecoder};
+use std::io::Cursor;
+
+/// 动图的一帧，已合成为完整画布
+pub struct Frame {
+    pub image: image::RgbaImage,
+    pub delay_ms: u32,
+}
+
+/// 各动图格式之间转换的统一表示
+pub struct Animation {
+    pub width: u32,
+    pub height: u32,
+    /// 播放次数，0 表示无限循环
+    pub loop_count: u32,
+    pub frames: Vec<Frame>,
+}
+
+impl Animation {
+    pub fn from_image(image: image::DynamicImage) -> Self {
+        let image = image.to_rgba8();
+        Animation {
+            width: image.width(),
+            height: image.height(),
+            loop_count: 0,
+            frames: vec![Frame { image, delay_ms: 0 }],
+        }
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+
+    /// 逐帧清理透明像素的颜色，规则与静态图片相同
+    #[cfg(feature = "webp")]
+    pub fn clean_alpha(self, mode: AlphaCleanup) -> Self {
+        if mode == AlphaCleanup::None {
+            return self;
+        }
+        let frames = self
+            .frames
+            .into_iter()
+            .map(|frame| Frame {
+                image: crate::alpha::clean(image::DynamicImage::ImageRgba8(frame.image), mode)
+                    .into_rgba8(),
+                delay_ms: frame.delay_ms,
+            })
+            .collect();
+        Animation { frames, ..self }
+    }
+
+    /// 按统一的尺寸调整规则缩放每一帧
+    pub fn resize(self, options: &ResizeOptions) -> Result<Self, CompressorError> {
+        // 逐帧智能裁剪的焦点会跳动，动图统一居中裁剪
+        let options = &ResizeOptions {
+            gravity: "center",
+            ..*options
+        };
+        let frames: Vec<Frame> = self
+            .frames
+            .into_iter()
+            .map(|frame| {
+                Ok(Frame {
+                    image: apply_resize(image::DynamicImage::ImageRgba8(frame.image), options)?
+                        .to_rgba8(),
+                    delay_ms: frame.delay_ms,
+                })
+            })
+            .collect::<Result<_, CompressorError>>()?;
+        let (width, height) = frames
+            .first()
+            .map(|frame| frame.image.dimensions())
+            .unwrap_or((self.width, self.height));
+        Ok(Animation {
+            width,
+            height,
+            loop_count: self.loop_count,
+            frames,
+        })
+    }
+}
+
+/// 解码为动图；静态图片返回单帧
+pub fn decode_animation(data: &[u8]) -> Result<Animation, CompressorError> {
+    match crate::utils::detect_format(data) {
+        Some("gif") => decode_gif(data),
+        Some("webp") if crate::utils::is_animated(data) => decode_webp(data),
+        Some("png") if crate::utils::is_animated(data) => decode_apng(data),
+        _ => Ok(Animation::from_image(crate::decode::load_image(data)?)),
+    }
+}
+
+fn decode_gif(data: &[u8]) -> Result<Animation, CompressorError> {
+    let decoder = image::codecs::gif::GifDecoder::new(Cursor::new(data))?;
+    let size = decoder.dimensions();
+    collect_frames(decoder, size, gif_loop_count(data))
+}
+
+fn decode_webp(data: &[u8]) -> Result<Animation, CompressorError> {
+    let decoder = image::codecs::webp::WebPDecoder::new(Cursor::new(data))?;
+    let size = decoder.dimensions();
+    collect_frames(decoder, size, webp_loop_count(data))
+}
+
+fn decode_apng(data: &[u8]) -> Result<Animation, CompressorError> {
+    let decoder = image::codecs::png::PngDecoder::new(Cursor::new(data))?;
+    let size = decoder.dimensions();
+    collect_frames(decoder.apng(), size, apng_loop_count(data))
+}
+
+/// 收集 image 库合成好的完整画布帧；每帧都是完整画布，累计大小按分配上限检查
+fn collect_frames<'a>(
+    decoder: impl AnimationDecoder<'a>,
+    (width, height): (u32, u32),
+    loop_count: u32,
+) -> Result<Animation, CompressorError> {
+    crate::decode::check_dimensions(width, height)?;
+    let frame_bytes = u64::from(width) * u64::from(height) * 4;
+
+    let mut total = 0;
+    let frames = decoder
+        .into_frames()
+        .map(|frame| {
+            total += frame_bytes;
+            crate::decode::check_alloc(total)?;
+            let frame = frame?;
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            Ok(Frame {
+                delay_ms: numer / denom.max(1),
+                image: frame.into_buffer(),
+            })
+        })
+        .collect::<Result<Vec<_>, CompressorError>>()?;
+
+    let first = frames
+        .first()
+        .ok_or_else(|| CompressorError::DecodeError("Animation contains no frames".to_string()))?;
+    let (width, height) = first.image.dimensions();
+
+    Ok(Animation {
+        width,
+        height,
+        loop_count,
+        frames,
+    })
+}
+
+/// acTL 块：帧数 (4 字节) + 播放次数 (4 字节，0 表示无限)
+fn apng_loop_count(data: &[u8]) -> u32 {
+    crate::utils::find_png_chunk(data, b"acTL")
+        .filter(|chunk| chunk.len() >= 8)
+        .map(|chunk| u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]))
+        .unwrap_or(0)
+}
+
+/// ANIM 块：背景色 (4 字节) + 循环次数 (2 字节，0 表示无限)
+fn webp_loop_count(data: &[u8]) -> u32 {
+    crate::utils::find_riff_chunk(data, b"ANIM")
+        .filter(|chunk| chunk.len() >= 6)
+        .map(|chunk| u32::from(u16::from_le_bytes([chunk[4], chunk[5]])))
+        .unwrap_or(0)
+}
+
+/// GIF 的 NETSCAPE 循环次数表示“额外重复次数”，这里换算为播放次数
+fn gif_loop_count(data: &[u8]) -> u32 {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let Ok(mut decoder) = options.read_info(Cursor::new(data)) else {
+        return 0;
+    };
+    // 循环扩展块位于第一帧之前
+    let _ = decoder.next_frame_info();
+    match decoder.repeat() {
+        gif::Repeat::Infinite => 0,
+        gif::Repeat::Finite(count) => u32::from(count) + 1,
+    }
+}
@@ -0,0 +1,255 @@
+use crate::alpha::AlphaCleanup;
+use crate::animation::decode_animation;
+use crate::errors::CompressorError;
+use crate::resize::{apply_resize, ResizeOptions};
+use crate::utils::{has_transparency, is_animated};
+
+/// codec_quality 的标定点：以 mozjpeg 的 quality 为基准
+const QUALITY_ANCHORS: [u8; 13] = [0, 20, 30, 40, 50, 60, 70, 75, 80, 85, 90, 95, 100];
+/// 各编码器在每个标定点上达到与 mozjpeg 相近 butteraugli 距离（3-范数）所需的 quality，
+/// 在四张缩到 384px 的照片上测得
+const WEBP_QUALITY: [u8; 13] = [0, 10, 25, 35, 45, 55, 71, 77, 80, 85, 90, 98, 100];
+const AVIF_QUALITY: [u8; 13] = [0, 25, 38, 48, 55, 59, 68, 74, 79, 86, 89, 92, 97];
+/// imagequant 的调色板量化误差较大，同等画质需要高得多的 quality
+const PALETTE_QUALITY: [u8; 13] = [0, 20, 55, 72, 78, 81, 85, 87, 88, 90, 94, 100, 100];
+
+/// 把以 mozjpeg 为基准的 quality 换算为 format 编码器的 quality，标定点之间线性插值。
+/// png、apng 与 gif 都经过 imagequant 量化，共用一张表
+pub fn codec_quality(format: &str, quality: u8) -> u8 {
+    let table = match format {
+        "webp" => &WEBP_QUALITY,
+        "avif" => &AVIF_QUALITY,
+        "png" | "apng" | "gif" => &PALETTE_QUALITY,
+        _ => return quality,
+    };
+    let quality = quality.min(100);
+    let upper = QUALITY_ANCHORS
+        .iter()
+        .position(|&anchor| anchor >= quality)
+        .unwrap_or(QUALITY_ANCHORS.len() - 1);
+    if upper == 0 || QUALITY_ANCHORS[upper] == quality {
+        return table[upper];
+    }
+    let (low, high) = (QUALITY_ANCHORS[upper - 1], QUALITY_ANCHORS[upper]);
+    let (from, to) = (i32::from(table[upper - 1]), i32::from(table[upper]));
+    let offset = i32::from(quality - low) * (to - from);
+    (from + (offset + i32::from(high - low) / 2) / i32::from(high - low)) as u8
+}
+
+/// 自动格式选择
+/// 只解码一次，分别编码为 PNG（量化）、JPEG、WebP 和 AVIF（按 feature 启用），返回体积最小的结果与其格式名。
+/// quality 以 mozjpeg 为基准，经 codec_quality 换算后再交给各编码器，使候选的画质大致相当。
+/// 某个候选编码失败时跳过，全部失败才返回错误。
+/// 动图只在支持动画的格式（GIF、APNG、WebP）之间选择。
+pub fn compress_auto(
+    data: &[u8],
+    quality: u8,
+    dithering: bool,
+    progressive: bool,
+    resize: &ResizeOptions,
+    auto_rotate: bool,
+    alpha: AlphaCleanup,
+) -> Result<(Vec<u8>, &'static str), CompressorError> {
+    if is_animated(data) {
+        return compress_animated(data, quality, dithering, resize);
+    }
+
+    let image = crate::decode::load_oriented(data, auto_rotate)?;
+    let image = crate::alpha::clean(image, alpha);
+    let image = apply_resize(image, resize)?;
+
+    let mut formats = vec!["png"];
+    // JPEG 不支持透明通道
+    if !has_transparency(&image) {
+        formats.push("jpeg");
+    }
+    if cfg!(feature = "webp") {
+        formats.push("webp");
+    }
+    if cfg!(feature = "avif") {
+        formats.push("avif");
+    }
+
+    // 各格式互不依赖，启用 threads 时同时编码
+    let candidates = crate::parallel::map(&formats, |format| {
+        let quality = codec_quality(format, quality);
+        match *format {
+            // 只有不透明的图片会编码为 JPEG，背景不起作用
+            "jpeg" => crate::jpeg::encode_jpeg(&image, quality, progressive, Default::default()),
+            #[cfg(feature = "webp")]
+            "webp" => crate::webp::encode_webp(&image, quality),
+            #[cfg(feature = "avif")]
+            "avif" => crate::avif::encode_avif(&image, quality),
+            _ => crate::png::encode_png(&image, quality, dithering, false),
+        }
+    });
+    smallest(&formats, candidates)
+}
+
+fn compress_animated(
+    data: &[u8],
+    quality: u8,
+    dithering: bool,
+    resize: &ResizeOptions,
+) -> Result<(Vec<u8>, &'static str), CompressorError> {
+    let animation = decode_animation(data)?.resize(resize)?;
+
+    let mut formats = vec!["gif", "apng"];
+    if cfg!(feature = "webp") {
+        formats.push("webp");
+    }
+
+    let candidates = crate::parallel::map(&formats, |format| {
+        let quality = codec_quality(format, quality);
+        match *format {
+            "apng" => crate::png::encode_apng(&animation, quality, dithering, false),
+            #[cfg(feature = "webp")]
+            "webp" => crate::webp::encode_animated_webp(&animation, quality),
+            _ => crate::gif::encode_gif(&animation, quality, dithering),
+        }
+    });
+    let (data, format) = smallest(&formats, candidates)?;
+    // APNG 的文件格式就是 PNG
+    Ok((data, if format == "apng" { "png" } else { format }))
+}
+
+/// 按顺序比较，体积相同时保留靠前的格式；失败的候选被跳过，全部失败时返回第一个错误。
+/// 取消时直接返回 Cancelled
+fn smallest(
+    formats: &[&'static str],
+    candidates: Vec<Result<Vec<u8>, CompressorError>>,
+) -> Result<(Vec<u8>, &'static str), CompressorError> {
+    let mut best: Option<(Vec<u8>, &'static str)> = None;
+    let mut first_error = None;
+    for (&format, candidate) in formats.iter().zip(candidates) {
+        match candidate {
+            Ok(data) => {
+                if best
+                    .as_ref()
+                    .is_none_or(|(best, _)| data.len() < best.len())
+                {
+                    best = Some((data, format));
+                }
+            }
+            Err(CompressorError::Cancelled) => return Err(CompressorError::Cancelled),
+            Err(err) => {
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+    best.ok_or_else(|| {
+        first_error.unwrap_or_else(|| {
+            CompressorError::EncodeError("No output format available".to_string())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::DynamicImage;
+
+    #[test]
+    fn codec_quality_interpolates_between_anchors() {
+        assert_eq!(codec_quality("jpeg", 63), 63);
+        assert_eq!(codec_quality("webp", 75), 77);
+        assert_eq!(codec_quality("avif", 100), 97);
+        assert_eq!(codec_quality("apng", 30), 55);
+        // 70 与 75 之间：71 → 77 的中点
+        assert_eq!(codec_quality("webp", 72), 73);
+        assert_eq!(codec_quality("gif", 25), 38);
+        assert_eq!(codec_quality("png", 200), 100);
+        for format in ["webp", "avif", "png"] {
+            let qualities: Vec<u8> = (0..=100).map(|q| codec_quality(format, q)).collect();
+            assert!(qualities.windows(2).all(|w| w[0] <= w[1]), "{format}");
+        }
+    }
+
+    #[test]
+    fn smallest_skips_failed_candidates() {
+        let candidates = vec![
+            Err(CompressorError::EncodeError("png".to_string())),
+            Ok(vec![0; 3]),
+            Ok(vec![0; 2]),
+            Ok(vec![0; 2]),
+        ];
+        let (data, format) = smallest(&["png", "jpeg", "webp", "avif"], candidates).unwrap();
+        assert_eq!((data.len(), format), (2, "webp"));
+
+        let candidates = vec![
+            Err(CompressorError::EncodeError("png".to_string())),
+            Err(CompressorError::EncodeError("jpeg".to_string())),
+        ];
+        match smallest(&["png", "jpeg"], candidates) {
+            Err(CompressorError::EncodeError(message)) => assert_eq!(message, "png"),
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let candidates = vec![Ok(vec![0; 1]), Err(CompressorError::Cancelled)];
+        assert!(matches!(
+            smallest(&["png", "jpeg"], candidates),
+            Err(CompressorError::Cancelled)
+        ));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn distance(image: &DynamicImage, encoded: &[u8]) -> f64 {
+        use butteraugli::{butteraugli, ButteraugliParams, Img, RGB8};
+
+        let to_img = |image: &DynamicImage| -> Img<Vec<RGB8>> {
+            let rgb = image.to_rgb8();
+            let pixels = rgb.pixels().map(|p| RGB8::new(p[0], p[1], p[2])).collect();
+            Img::new(pixels, rgb.width() as usize, rgb.height() as usize)
+        };
+        let decoded = crate::decode::load_image(encoded).unwrap();
+        butteraugli(
+            to_img(image).as_ref(),
+            to_img(&decoded).as_ref(),
+            &ButteraugliParams::default(),
+        )
+        .unwrap()
+        .pnorm_3
+    }
+
+    /// webp crate 附带的 lake.jpg 缩到 256px（MIT/Apache-2.0）
+    #[cfg(not(target_arch = "wasm32"))]
+    fn photo() -> DynamicImage {
+        crate::decode::load_image(include_bytes!("../tests/fixtures/lake.jpg")).unwrap()
+    }
+
+    /// 换算后的 quality 应让候选的 butteraugli 距离与同 quality 的 mozjpeg 相差不超过 20%
+    #[cfg(not(target_arch = "wasm32"))]
+    fn assert_closer_to_jpeg(
+        format: &str,
+        encode: impl Fn(&DynamicImage, u8) -> Result<Vec<u8>, CompressorError>,
+    ) {
+        let image = photo();
+        for quality in [50, 75, 90] {
+            let jpeg =
+                crate::jpeg::encode_jpeg(&image, quality, false, Default::default()).unwrap();
+            let target = distance(&image, &jpeg);
+            let mapped = distance(
+                &image,
+                &encode(&image, codec_quality(format, quality)).unwrap(),
+            );
+            assert!(
+                (0.8..1.2).contains(&(mapped / target)),
+                "{format} q{quality}: jpeg {target:.3}, mapped {mapped:.3}"
+            );
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn png_quality_matches_jpeg_distance() {
+        assert_closer_to_jpeg("png", |image, quality| {
+            crate::png::encode_png(image, quality, true, false)
+        });
+    }
+
+    #[cfg(all(feature = "webp", not(target_arch = "wasm32")))]
+    #[test]
+    fn webp_quality_matches_jpeg_distance() {
+        assert_closer_to_jpeg("webp", crate::webp::encode_webp);
+    }
+}
@@ -7,38 +7,52 @@ use crate::errors::CompressorError;
 pub fn compress_avif(
     data: &[u8],
     quality: u8,
+    alpha: crate::alpha::AlphaCleanup,
 ) -> Result<Vec<u8>, CompressorError> {
-    use ravif::{Img, Encoder};
-    use rgb::RGBA;
+    // ravif 只能编码静态图片，拒绝动图以免静默丢帧
+    if crate::utils::is_animated(data) {
+        return Err(CompressorError::EncodeError(
+            "Animated AVIF encoding is not supported".to_string(),
+        ));
+    }
 
     // 解码原始图片
-    let img = image::load_from_memory(data)
-        .map_err(|e| CompressorError::EncodeError(format!("Failed to decode image: {}", e)))?;
+    let img = crate::decode::load_image(data)?;
+    let img = crate::alpha::clean(img, alpha);
+
+    encode_avif(&img, quality)
+}
+
+#[cfg(feature = "avif")]
+pub fn encode_avif(
+    img: &image::DynamicImage,
+    quality: u8,
+) -> Result<Vec<u8>, CompressorError> {
+    use ravif::{Img, Encoder};
 
     // 转换质量参数 (0-100 -> 1-100)
     let quality = quality.max(1).min(100) as f32;
 
     // 统一转换为 RGBA 格式进行编码
-    let rgba_img = img.to_rgba8();
+    let rgba_img = crate::utils::rgba8(img);
     let (width, height) = rgba_img.dimensions();
-    let pixels: Vec<RGBA<u8>> = rgba_img
-        .pixels()
-        .map(|p| RGBA {
-            r: p[0],
-            g: p[1],
-            b: p[2],
-            a: p[3],
-        })
-        .collect();
-
-    let img_ref = Img::new(pixels.as_slice(), width as usize, height as usize);
+
+    let img_ref = Img::new(
+        crate::utils::as_rgba_pixels(&rgba_img),
+        width as usize,
+        height as usize,
+    );
 
     let encoder = Encoder::new()
         .with_quality(quality)
         .with_alpha_quality(quality);
 
+    // ravif 没有进度回调，只能在编码前后检查取消
+    crate::progress::report("encode", 0.0)?;
     let result = encoder.encode_rgba(img_ref)
         .map_err(|e| CompressorError::EncodeError(format!("AVIF encoding failed: {}", e)))?;
+    crate::utils::recycle_image(rgba_img);
+    crate::progress::report("encode", 1.0)?;
 
     Ok(result.avif_file)
 }
@@ -47,6 +61,7 @@ pub fn compress_avif(
 pub fn compress_avif(
     _data: &[u8],
     _quality: u8,
+    _alpha: crate::alpha::AlphaCleanup,
 ) -> Result<Vec<u8>, CompressorError> {
     // AVIF feature 未启用，返回错误让主逻辑使用浏览器 Canvas API fallback
     Err(CompressorError::EncodeError(
@@ -0,0 +1,489 @@
+use crate::errors::CompressorError;
+use image::imageops;
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::include::dav1d::dav1d::{Dav1dContext, Dav1dSettings};
+use rav1d::include::dav1d::headers::{
+    DAV1D_MC_BT2020_CL, DAV1D_MC_BT2020_NCL, DAV1D_MC_BT709, DAV1D_MC_FCC, DAV1D_MC_IDENTITY,
+    DAV1D_MC_SMPTE240, DAV1D_MC_SMPTE_YCGCO, DAV1D_MC_UNKNOWN, DAV1D_PIXEL_LAYOUT_I400,
+    DAV1D_PIXEL_LAYOUT_I420, DAV1D_PIXEL_LAYOUT_I422,
+};
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use rav1d::src::lib::{
+    dav1d_close, dav1d_data_create, dav1d_data_unref, dav1d_default_settings, dav1d_get_picture,
+    dav1d_open, dav1d_picture_unref, dav1d_send_data,
+};
+use std::mem::MaybeUninit;
+use std::ptr::NonNull;
+use zenavif_parse::{AvifParser, CleanAperture, ColorInformation, ImageMirror, ImageRotation};
+
+/// 解码 AVIF 主图像（含可选的 Alpha 辅助图像）
+/// AV1 码流由纯 Rust 的 rav1d 解码，再按序列头中的矩阵系数与取值范围转换为 RGBA
+/// 输出不保留色彩配置文件，带 ICC 时转换到 sRGB，并应用容器中的裁剪、旋转与镜像
+pub fn decode_avif(data: &[u8]) -> Result<image::DynamicImage, CompressorError> {
+    let avif = AvifParser::from_bytes(data).map_err(parse_error)?;
+
+    let color = decode_av1(&avif.primary_data().map_err(parse_error)?)?;
+    let alpha = match avif.alpha_data() {
+        Some(alpha) => Some(decode_av1(&alpha.map_err(parse_error)?)?),
+        None => None,
+    };
+
+    let (width, height) = color.size();
+    crate::decode::check_dimensions(width as u32, height as u32)?;
+    if let Some(alpha) = &alpha {
+        if alpha.size() != (width, height) {
+            return Err(CompressorError::DecodeError(
+                "AVIF alpha size does not match color image".to_string(),
+            ));
+        }
+    }
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let [r, g, b] = color.rgb(x, y);
+            let a = alpha.as_ref().map_or(1.0, |alpha| alpha.luma(0, x, y));
+            let [r, g, b] = if avif.premultiplied_alpha() && a > 0.0 {
+                [r / a, g / a, b / a]
+            } else {
+                [r, g, b]
+            };
+            rgba.extend([r, g, b, a].map(to_u8));
+        }
+    }
+
+    if let Some(ColorInformation::IccProfile(icc)) = avif.color_info() {
+        crate::icc::to_srgb(&mut rgba, icc);
+    }
+
+    let buffer = image::RgbaImage::from_raw(width as u32, height as u32, rgba)
+        .ok_or_else(|| CompressorError::DecodeError("Invalid AVIF image size".to_string()))?;
+    let buffer = transform(
+        buffer,
+        avif.clean_aperture(),
+        avif.rotation(),
+        avif.mirror(),
+    );
+    Ok(image::DynamicImage::ImageRgba8(buffer))
+}
+
+/// 按 MIAF 规定的顺序依次应用 clap、irot、imir
+fn transform(
+    image: image::RgbaImage,
+    clap: Option<&CleanAperture>,
+    rotation: Option<&ImageRotation>,
+    mirror: Option<&ImageMirror>,
+) -> image::RgbaImage {
+    let image = match clap.and_then(|clap| crop_rect(clap, image.width(), image.height())) {
+        Some((x, y, width, height)) => imageops::crop_imm(&image, x, y, width, height).to_image(),
+        None => image,
+    };
+    // irot 为逆时针角度
+    let image = match rotation.map(|rotation| rotation.angle) {
+        Some(90) => imageops::rotate270(&image),
+        Some(180) => imageops::rotate180(&image),
+        Some(270) => imageops::rotate90(&image),
+        _ => image,
+    };
+    // axis 0 以竖直轴镜像（左右翻转），1 以水平轴镜像（上下翻转）
+    match mirror.map(|mirror| mirror.axis) {
+        Some(0) => imageops::flip_horizontal(&image),
+        Some(1) => imageops::flip_vertical(&image),
+        _ => image,
+    }
+}
+
+/// clap 以图像中心的偏移描述裁剪区域，换算为左上角坐标
+/// 不是整数像素或越界的 clap 视为无效并忽略，与 libavif 的校验规则一致
+fn crop_rect(clap: &CleanAperture, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+    fn ratio(numerator: i64, denominator: u32) -> Option<f64> {
+        (denominator != 0).then(|| numerator as f64 / f64::from(denominator))
+    }
+    fn whole(value: f64) -> Option<i64> {
+        (value.fract() == 0.0).then_some(value as i64)
+    }
+
+    let crop_width = whole(ratio(clap.width_n.into(), clap.width_d)?)?;
+    let crop_height = whole(ratio(clap.height_n.into(), clap.height_d)?)?;
+    let horizontal = ratio(clap.horiz_off_n.into(), clap.horiz_off_d)?;
+    let vertical = ratio(clap.vert_off_n.into(), clap.vert_off_d)?;
+    let x = whole(horizontal + (i64::from(width) - crop_width) as f64 / 2.0)?;
+    let y = whole(vertical + (i64::from(height) - crop_height) as f64 / 2.0)?;
+
+    let fits = crop_width > 0
+        && crop_height > 0
+        && x >= 0
+        && y >= 0
+        && x + crop_width <= i64::from(width)
+        && y + crop_height <= i64::from(height);
+    fits.then_some((x as u32, y as u32, crop_width as u32, crop_height as u32))
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn parse_error(err: zenavif_parse::Error) -> CompressorError {
+    CompressorError::DecodeError(err.to_string())
+}
+
+fn decode_error(stage: &str) -> CompressorError {
+    CompressorError::DecodeError(format!("AV1 {stage} failed"))
+}
+
+/// 单线程、无帧延迟地解码 AVIF 图像项中的一帧
+fn decode_av1(obu: &[u8]) -> Result<Picture, CompressorError> {
+    let decoder = Decoder::open()?;
+
+    let mut input = Input(Dav1dData::default());
+    let buf = unsafe { dav1d_data_create(Some(NonNull::from(&mut input.0)), obu.len()) };
+    if buf.is_null() {
+        return Err(decode_error("buffer allocation"));
+    }
+    unsafe { std::ptr::copy_nonoverlapping(obu.as_ptr(), buf, obu.len()) };
+
+    let mut picture = Picture(Dav1dPicture::default());
+    loop {
+        if input.0.sz > 0 {
+            let result = unsafe { dav1d_send_data(decoder.0, Some(NonNull::from(&mut input.0))) };
+            if result.0 < 0 && result.0 != -libc::EAGAIN {
+                return Err(decode_error("send data"));
+            }
+        }
+
+        let result = unsafe { dav1d_get_picture(decoder.0, Some(NonNull::from(&mut picture.0))) };
+        if result.0 == 0 {
+            return Ok(picture);
+        }
+        // 数据已全部送入仍拿不到图像，说明码流不完整
+        if result.0 != -libc::EAGAIN || input.0.sz == 0 {
+            return Err(decode_error("get picture"));
+        }
+    }
+}
+
+struct Decoder(Option<Dav1dContext>);
+
+impl Decoder {
+    fn open() -> Result<Self, CompressorError> {
+        let mut settings = MaybeUninit::<Dav1dSettings>::uninit();
+        unsafe { dav1d_default_settings(NonNull::from(&mut settings).cast()) };
+        let mut settings = unsafe { settings.assume_init() };
+        settings.n_threads = 1;
+        settings.max_frame_delay = 1;
+        // 只输出最高的空间层
+        settings.all_layers = 0;
+        // 解码前按像素上限拒绝超大帧
+        settings.frame_size_limit =
+            crate::decode::limits().max_pixels.min(u64::from(u32::MAX)) as u32;
+
+        let mut decoder = Decoder(None);
+        let result = unsafe {
+            dav1d_open(
+                Some(NonNull::from(&mut decoder.0)),
+                Some(NonNull::from(&mut settings)),
+            )
+        };
+        if result.0 < 0 {
+            return Err(decode_error("decoder initialization"));
+        }
+        Ok(decoder)
+    }
+}
+
+impl Drop for Decoder {
+    fn drop(&mut self) {
+        unsafe { dav1d_close(Some(NonNull::from(&mut self.0))) };
+    }
+}
+
+struct Input(Dav1dData);
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        unsafe { dav1d_data_unref(Some(NonNull::from(&mut self.0))) };
+    }
+}
+
+struct Picture(Dav1dPicture);
+
+impl Drop for Picture {
+    fn drop(&mut self) {
+        unsafe { dav1d_picture_unref(Some(NonNull::from(&mut self.0))) };
+    }
+}
+
+impl Picture {
+    fn size(&self) -> (usize, usize) {
+        (self.0.p.w as usize, self.0.p.h as usize)
+    }
+
+    /// 读取平面上的原始采样值，高位深按 16 位存储
+    fn sample(&self, plane: usize, x: usize, y: usize) -> f32 {
+        let Some(base) = self.0.data[plane] else {
+            return 0.0;
+        };
+        let stride = self.0.stride[plane.min(1)];
+        unsafe {
+            let row = base.as_ptr().cast::<u8>().offset(y as isize * stride);
+            if self.0.p.bpc > 8 {
+                f32::from(*row.cast::<u16>().add(x))
+            } else {
+                f32::from(*row.add(x))
+            }
+        }
+    }
+
+    fn full_range(&self) -> bool {
+        self.0
+            .seq_hdr
+            .is_some_and(|header| unsafe { header.as_ref().color_range != 0 })
+    }
+
+    fn matrix(&self) -> u32 {
+        self.0
+            .seq_hdr
+            .map_or(DAV1D_MC_UNKNOWN, |header| unsafe { header.as_ref().mtrx })
+    }
+
+    /// 亮度归一化到 0..1（GBR 存储时三个平面都按亮度处理）
+    fn luma(&self, plane: usize, x: usize, y: usize) -> f32 {
+        normalize_luma(
+            self.sample(plane, x, y),
+            self.0.p.bpc as u32,
+            self.full_range(),
+        )
+    }
+
+    /// 色度归一化到 -0.5..0.5，子采样时对相邻色度样本做双线性插值
+    fn chroma(&self, plane: usize, x: usize, y: usize) -> f32 {
+        let (subsample_x, subsample_y) = match self.0.p.layout {
+            DAV1D_PIXEL_LAYOUT_I420 => (true, true),
+            DAV1D_PIXEL_LAYOUT_I422 => (true, false),
+            _ => (false, false),
+        };
+        let (width, height) = self.size();
+        let (x0, x1, wx) = chroma_taps(x, subsample_x, width);
+        let (y0, y1, wy) = chroma_taps(y, subsample_y, height);
+        let row = |cy| self.sample(plane, x0, cy) * (1.0 - wx) + self.sample(plane, x1, cy) * wx;
+        normalize_chroma(
+            row(y0) * (1.0 - wy) + row(y1) * wy,
+            self.0.p.bpc as u32,
+            self.full_range(),
+        )
+    }
+
+    fn rgb(&self, x: usize, y: usize) -> [f32; 3] {
+        let luma = self.luma(0, x, y);
+        if self.0.p.layout == DAV1D_PIXEL_LAYOUT_I400 {
+            return [luma; 3];
+        }
+        // GBR 存储：Y = G，U = B，V = R
+        if self.matrix() == DAV1D_MC_IDENTITY {
+            return [self.luma(2, x, y), luma, self.luma(1, x, y)];
+        }
+        ycbcr_to_rgb(
+            luma,
+            self.chroma(1, x, y),
+            self.chroma(2, x, y),
+            self.matrix(),
+        )
+    }
+}
+
+/// 子采样方向上取左右（上下）两个色度样本及后者的权重
+/// 色度样本位于两个亮度样本正中，边缘处复制最外侧样本
+fn chroma_taps(pos: usize, subsampled: bool, luma_len: usize) -> (usize, usize, f32) {
+    if !subsampled {
+        return (pos, pos, 0.0);
+    }
+    let last = (luma_len.div_ceil(2) - 1) as isize;
+    let center = (pos as f32 + 0.5) / 2.0 - 0.5;
+    let first = center.floor();
+    let weight = center - first;
+    let first = first as isize;
+    (
+        first.clamp(0, last) as usize,
+        (first + 1).clamp(0, last) as usize,
+        weight,
+    )
+}
+
+fn normalize_luma(value: f32, bpc: u32, full_range: bool) -> f32 {
+    if full_range {
+        value / ((1u32 << bpc) - 1) as f32
+    } else {
+        let scale = (1u32 << (bpc - 8)) as f32;
+        (value - 16.0 * scale) / (219.0 * scale)
+    }
+}
+
+fn normalize_chroma(value: f32, bpc: u32, full_range: bool) -> f32 {
+    let scale = (1u32 << (bpc - 8)) as f32;
+    if full_range {
+        (value - 128.0 * scale) / ((1u32 << bpc) - 1) as f32
+    } else {
+        (value - 128.0 * scale) / (224.0 * scale)
+    }
+}
+
+/// 归一化的 Y（0..1）与 Cb、Cr（-0.5..0.5）按矩阵系数转为 RGB
+fn ycbcr_to_rgb(luma: f32, cb: f32, cr: f32, matrix: u32) -> [f32; 3] {
+    let (kr, kb) = match matrix {
+        DAV1D_MC_SMPTE_YCGCO => {
+            let base = luma - cb;
+            return [base + cr, luma + cb, base - cr];
+        }
+        DAV1D_MC_BT709 => (0.2126, 0.0722),
+        DAV1D_MC_FCC => (0.30, 0.11),
+        DAV1D_MC_SMPTE240 => (0.212, 0.087),
+        DAV1D_MC_BT2020_NCL | DAV1D_MC_BT2020_CL => (0.2627, 0.0593),
+        // 未指定或其他矩阵按 BT.601 处理，与 libavif 一致
+        _ => (0.299, 0.114),
+    };
+    let kg = 1.0 - kr - kb;
+    let r = luma + 2.0 * (1.0 - kr) * cr;
+    let b = luma + 2.0 * (1.0 - kb) * cb;
+    let g = (luma - kr * r - kb * b) / kg;
+    [r, g, b]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RGB（0..1）按矩阵正向转换并量化为 bpc 位样本
+    fn encode(rgb: [f32; 3], matrix: u32, bpc: u32, full_range: bool) -> [f32; 3] {
+        let [r, g, b] = rgb;
+        let (luma, cb, cr) = match matrix {
+            DAV1D_MC_SMPTE_YCGCO => (
+                0.25 * r + 0.5 * g + 0.25 * b,
+                -0.25 * r + 0.5 * g - 0.25 * b,
+                0.5 * (r - b),
+            ),
+            _ => {
+                let (kr, kb) = match matrix {
+                    DAV1D_MC_BT709 => (0.2126, 0.0722),
+                    DAV1D_MC_BT2020_NCL => (0.2627, 0.0593),
+                    _ => (0.299, 0.114),
+                };
+                let luma = kr * r + (1.0 - kr - kb) * g + kb * b;
+                (
+                    luma,
+                    (b - luma) / (2.0 * (1.0 - kb)),
+                    (r - luma) / (2.0 * (1.0 - kr)),
+                )
+            }
+        };
+        let max = ((1u32 << bpc) - 1) as f32;
+        let scale = (1u32 << (bpc - 8)) as f32;
+        let quantize = |value: f32| value.round().clamp(0.0, max);
+        if full_range {
+            [
+                quantize(luma * max),
+                quantize(cb * max + 128.0 * scale),
+                quantize(cr * max + 128.0 * scale),
+            ]
+        } else {
+            [
+                quantize(16.0 * scale + luma * 219.0 * scale),
+                quantize(128.0 * scale + cb * 224.0 * scale),
+                quantize(128.0 * scale + cr * 224.0 * scale),
+            ]
+        }
+    }
+
+    #[test]
+    fn chroma_taps_interpolate_between_sited_samples() {
+        assert_eq!(chroma_taps(5, false, 9), (5, 5, 0.0));
+        // 色度样本 0 居于亮度 0、1 之间，边缘复制
+        assert_eq!(chroma_taps(0, true, 9), (0, 0, 0.75));
+        assert_eq!(chroma_taps(1, true, 9), (0, 1, 0.25));
+        assert_eq!(chroma_taps(2, true, 9), (0, 1, 0.75));
+        assert_eq!(chroma_taps(9, true, 10), (4, 4, 0.25));
+    }
+
+    #[test]
+    fn container_transforms_are_applied_in_order() {
+        // 4x2 图像，像素值为 (x, y)
+        let image = image::RgbaImage::from_fn(4, 2, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+        let pixels = |image: &image::RgbaImage| -> Vec<(u8, u8)> {
+            image.pixels().map(|p| (p[0], p[1])).collect()
+        };
+
+        // 居中裁剪 2x2 后向右偏移 1 像素
+        let clap = CleanAperture {
+            width_n: 2,
+            width_d: 1,
+            height_n: 2,
+            height_d: 1,
+            horiz_off_n: 1,
+            horiz_off_d: 1,
+            vert_off_n: 0,
+            vert_off_d: 1,
+        };
+        let cropped = transform(image.clone(), Some(&clap), None, None);
+        assert_eq!(pixels(&cropped), [(2, 0), (3, 0), (2, 1), (3, 1)]);
+
+        // 逆时针旋转 90°：原右上角到左上角
+        let rotated = transform(
+            image.clone(),
+            None,
+            Some(&ImageRotation { angle: 90 }),
+            None,
+        );
+        assert_eq!(rotated.dimensions(), (2, 4));
+        assert_eq!(pixels(&rotated)[..2], [(3, 0), (3, 1)]);
+
+        let mirrored = transform(image.clone(), None, None, Some(&ImageMirror { axis: 0 }));
+        assert_eq!(pixels(&mirrored)[..4], [(3, 0), (2, 0), (1, 0), (0, 0)]);
+        let mirrored = transform(image.clone(), None, None, Some(&ImageMirror { axis: 1 }));
+        assert_eq!(pixels(&mirrored)[..4], [(0, 1), (1, 1), (2, 1), (3, 1)]);
+
+        // 先裁剪再旋转再镜像
+        let combined = transform(
+            image.clone(),
+            Some(&clap),
+            Some(&ImageRotation { angle: 270 }),
+            Some(&ImageMirror { axis: 1 }),
+        );
+        assert_eq!(pixels(&combined), [(3, 1), (3, 0), (2, 1), (2, 0)]);
+
+        // 非整数像素的 clap 被忽略
+        let invalid = CleanAperture { width_d: 3, ..clap };
+        assert_eq!(transform(image.clone(), Some(&invalid), None, None), image);
+    }
+
+    #[test]
+    fn ycbcr_round_trips_to_rgb() {
+        let matrices = [
+            DAV1D_MC_UNKNOWN,
+            DAV1D_MC_BT709,
+            DAV1D_MC_BT2020_NCL,
+            DAV1D_MC_SMPTE_YCGCO,
+        ];
+        for matrix in matrices {
+            for (bpc, tolerance) in [(8, 2.0), (10, 0.5)] {
+                for full_range in [true, false] {
+                    for rgb in (0..4096).map(|i| [i >> 8, (i >> 4) & 15, i & 15]) {
+                        let rgb = rgb.map(|channel| channel as f32 * 17.0 / 255.0);
+                        let [luma, cb, cr] = encode(rgb, matrix, bpc, full_range);
+                        let decoded = ycbcr_to_rgb(
+                            normalize_luma(luma, bpc, full_range),
+                            normalize_chroma(cb, bpc, full_range),
+                            normalize_chroma(cr, bpc, full_range),
+                            matrix,
+                        );
+                        for (expected, actual) in rgb.iter().zip(decoded) {
+                            assert!(
+                                ((expected - actual) * 255.0).abs() <= tolerance,
+                                "matrix {matrix}, {bpc} bit, full range {full_range}: \
+                                 {rgb:?} decoded as {decoded:?}"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use crate::config::{Config, Settings};
+use crate::errors::CompressorError;
+use crate::CompressOptions;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// 目录处理时收集的扩展名（不区分大小写）
+const IMAGE_EXTENSIONS: [&str; 14] = [
+    "png", "jpg", "jpeg", "webp", "gif", "bmp", "tif", "tiff", "ico", "svg", "heic", "heif",
+    "avif", "jxl",
+];
+
+/// 批量任务的一个输入
+#[derive(Debug, Clone)]
+pub struct BatchInput {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// 单个输入的压缩结果，顺序与输入一致
+#[derive(Debug)]
+pub struct BatchOutput {
+    pub name: String,
+    pub original_size: u64,
+    pub result: Result<Vec<u8>, CompressorError>,
+}
+
+/// 每完成一个文件回调一次；回调可能在任意工作线程上执行
+#[derive(Debug)]
+pub struct BatchProgress<'a> {
+    pub name: &'a str,
+    /// 已完成的文件数（含本文件）
+    pub completed: usize,
+    pub total: usize,
+    pub original_size: u64,
+    /// 成功时为输出字节数
+    pub result: Result<u64, &'a CompressorError>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchFailure {
+    pub name: String,
+    pub error: String,
+}
+
+/// 汇总结果，字节数只统计成功的文件
+#[derive(Debug, Clone, Default)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub original_bytes: u64,
+    pub output_bytes: u64,
+    pub failures: Vec<BatchFailure>,
+}
+
+impl BatchSummary {
+    /// 节省的字节数，输出变大时为 0
+    pub fn saved_bytes(&self) -> u64 {
+        self.original_bytes.saturating_sub(self.output_bytes)
+    }
+
+    /// 节省比例 0.0..=1.0
+    pub fn saved_ratio(&self) -> f64 {
+        if self.original_bytes == 0 {
+            return 0.0;
+        }
+        self.saved_bytes() as f64 / self.original_bytes as f64
+    }
+
+    fn record(&mut self, name: &str, original_size: u64, result: Result<u64, &CompressorError>) {
+        self.total += 1;
+        match result {
+            Ok(output_size) => {
+                self.succeeded += 1;
+                self.original_bytes += original_size;
+                self.output_bytes += output_size;
+            }
+            Err(err) => self.failures.push(BatchFailure {
+                name: name.to_string(),
+                error: err.to_string(),
+            }),
+        }
+    }
+}
+
+/// 用同一组参数压缩多个输入，底层与 compress_image 相同。
+/// 启用 threads feature 时在 rayon 线程池中并行处理，否则在调用线程上逐个处理
+pub fn compress_batch<F>(
+    inputs: &[BatchInput],
+    format: &str,
+    quality: u8,
+    options: &CompressOptions,
+    on_progress: F,
+) -> (Vec<BatchOutput>, BatchSummary)
+where
+    F: Fn(&BatchProgress) + Sync + Send,
+{
+    let completed = AtomicUsize::new(0);
+    let outputs = crate::parallel::map(inputs, |input| {
+        let result = crate::compress(&input.data, format, quality, options);
+        let original_size = input.data.len() as u64;
+        on_progress(&BatchProgress {
+            name: &input.name,
+            completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+            total: inputs.len(),
+            original_size,
+            result: output_size(&result),
+        });
+        BatchOutput {
+            name: input.name.clone(),
+            original_size,
+            result,
+        }
+    });
+
+    let mut summary = BatchSummary::default();
+    for output in &outputs {
+        summary.record(
+            &output.name,
+            output.original_size,
+            output_size(&output.result),
+        );
+    }
+    (outputs, summary)
+}
+
+/// 压缩 input_dir 下（含子目录）的全部图片，按相同的相对路径写入 output_dir。
+/// 扩展名按输出格式改写；同一目录下主文件名相同的输入（photo.png 与 photo.jpg）保留原扩展名，
+/// 写为 photo.png.jpg 与 photo.jpg.jpg，避免互相覆盖。不跟随符号链接目录。
+/// 逐个读写文件，不会同时在内存中保留全部结果；未启用 threads 时不并行
+pub fn compress_directory<F>(
+    input_dir: &Path,
+    output_dir: &Path,
+    format: &str,
+    quality: u8,
+    options: &CompressOptions,
+    on_progress: F,
+) -> std::io::Result<BatchSummary>
+where
+    F: Fn(&BatchProgress) + Sync + Send,
+{
+    let settings = Settings {
+        format: format.to_string(),
+        quality,
+        options: options.clone(),
+    };
+    process_directory(input_dir, output_dir, |_| settings.clone(), on_progress)
+}
+
+/// 与 compress_directory 相同，但每个文件的设置按配置文件中的规则决定
+pub fn compress_directory_with_config<F>(
+    input_dir: &Path,
+    output_dir: &Path,
+    config: &Config,
+    on_progress: F,
+) -> std::io::Result<BatchSummary>
+where
+    F: Fn(&BatchProgress) + Sync + Send,
+{
+    process_directory(
+        input_dir,
+        output_dir,
+        |relative| config.resolve(relative),
+        on_progress,
+    )
+}
+
+fn process_directory<S, F>(
+    input_dir: &Path,
+    output_dir: &Path,
+    settings: S,
+    on_progress: F,
+) -> std::io::Result<BatchSummary>
+where
+    S: Fn(&str) -> Settings + Sync + Send,
+    F: Fn(&BatchProgress) + Sync + Send,
+{
+    let mut files = Vec::new();
+    collect_images(input_dir, &mut files)?;
+    files.sort();
+
+    let mut stems = HashMap::new();
+    for path in &files {
+        *stems.entry(path.with_extension("")).or_insert(0) += 1;
+    }
+    // 保留原扩展名后仍可能撞名（photo.png 与 photo.png.jpg），后写的文件报错而不是覆盖
+    let written = Mutex::new(HashSet::new());
+
+    let completed = AtomicUsize::new(0);
+    let reports = crate::parallel::map(&files, |path| {
+        let relative = path.strip_prefix(input_dir).unwrap_or(path);
+        // 规则按 / 分隔的相对路径匹配
+        let name = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let target = output_dir.join(relative);
+        let keep_extension = stems[&path.with_extension("")] > 1;
+        let (original_size, result) =
+            compress_file(path, &target, keep_extension, &written, &settings(&name));
+        on_progress(&BatchProgress {
+            name: &name,
+            completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+            total: files.len(),
+            original_size,
+            result: result.as_ref().copied(),
+        });
+        (name, original_size, result)
+    });
+
+    let mut summary = BatchSummary::default();
+    for (name, original_size, result) in &reports {
+        summary.record(name, *original_size, result.as_ref().copied());
+    }
+    Ok(summary)
+}
+
+fn output_size(result: &Result<Vec<u8>, CompressorError>) -> Result<u64, &CompressorError> {
+    result.as_ref().map(|data| data.len() as u64)
+}
+
+/// 读取、压缩并写出一个文件，返回原始大小与输出大小。
+/// keep_extension 时在原文件名后追加输出扩展名；written 记录已占用的输出路径
+fn compress_file(
+    path: &Path,
+    target: &Path,
+    keep_extension: bool,
+    written: &Mutex<HashSet<PathBuf>>,
+    settings: &Settings,
+) -> (u64, Result<u64, CompressorError>) {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(err) => return (0, Err(CompressorError::DecodeError(err.to_string()))),
+    };
+    let result = crate::compress(&data, &settings.format, settings.quality, &settings.options)
+        .and_then(|output| {
+            let extension = match crate::utils::detect_format(&output) {
+                Some("jpeg") => "jpg",
+                Some(format) => format,
+                None => "bin",
+            };
+            let target = if keep_extension {
+                let mut name = target.as_os_str().to_owned();
+                name.push(".");
+                name.push(extension);
+                PathBuf::from(name)
+            } else {
+                target.with_extension(extension)
+            };
+            if !written.lock().unwrap().insert(target.clone()) {
+                return Err(CompressorError::EncodeError(format!(
+                    "Output path {} is already used by another input",
+                    target.display()
+                )));
+            }
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+            }
+            std::fs::write(&target, &output)
+                .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+            Ok(output.len() as u64)
+        });
+    (data.len() as u64, result)
+}
+
+fn collect_images(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        // file_type 不跟随符号链接，指回上层的链接不会造成无限递归
+        if entry.file_type()?.is_dir() {
+            collect_images(&path, files)?;
+        } else if path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+            })
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(format: image::ImageFormat) -> Vec<u8> {
+        let image =
+            image::RgbImage::from_fn(16, 16, |x, y| image::Rgb([x as u8 * 16, y as u8 * 16, 128]));
+        let mut out = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut out, format).unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn compress_directory_mirrors_layout_and_reports_failures() {
+        let root = std::env::temp_dir().join(format!("tinylocal-batch-{}", std::process::id()));
+        let (input, output) = (root.join("in"), root.join("out"));
+        std::fs::create_dir_all(input.join("sub")).unwrap();
+        std::fs::write(input.join("a.png"), encoded(image::ImageFormat::Png)).unwrap();
+        std::fs::write(input.join("notes.txt"), "not an image").unwrap();
+        std::fs::write(input.join("sub/b.png"), encoded(image::ImageFormat::Png)).unwrap();
+        std::fs::write(
+            input.join("sub/photo.png"),
+            encoded(image::ImageFormat::Png),
+        )
+        .unwrap();
+        std::fs::write(
+            input.join("sub/photo.jpg"),
+            encoded(image::ImageFormat::Jpeg),
+        )
+        .unwrap();
+        std::fs::write(input.join("sub/broken.jpg"), b"not a jpeg").unwrap();
+        // 指回输入根目录的链接不应被跟随
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&input, input.join("sub/loop")).unwrap();
+
+        let reported = Mutex::new(Vec::new());
+        let summary = compress_directory(
+            &input,
+            &output,
+            "jpeg",
+            75,
+            &CompressOptions::default(),
+            |progress| reported.lock().unwrap().push(progress.name.to_string()),
+        )
+        .unwrap();
+
+        let mut reported = reported.into_inner().unwrap();
+        reported.sort();
+        assert_eq!(
+            reported,
+            [
+                "a.png",
+                "sub/b.png",
+                "sub/broken.jpg",
+                "sub/photo.jpg",
+                "sub/photo.png"
+            ]
+        );
+        assert_eq!((summary.total, summary.succeeded), (5, 4));
+        assert_eq!(summary.failures.len(), 1);
+        assert_eq!(summary.failures[0].name, "sub/broken.jpg");
+
+        for written in [
+            "a.jpg",
+            "sub/b.jpg",
+            "sub/photo.png.jpg",
+            "sub/photo.jpg.jpg",
+        ] {
+            let data = std::fs::read(output.join(written)).unwrap();
+            assert_eq!(
+                crate::utils::detect_format(&data),
+                Some("jpeg"),
+                "{written}"
+            );
+        }
+        assert!(!output.join("sub/photo.jpg").exists());
+        assert!(!output.join("sub/broken.jpg").exists());
+        assert!(!output.join("sub/loop").exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
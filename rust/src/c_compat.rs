@@ -30,6 +30,7 @@ pub unsafe extern "C" fn malloc(size: usize) -> *mut u8 {
     }
 
     (ptr as *mut usize).write(size);
+    crate::memory::record_c_alloc(size);
     ptr.add(HEADER_SIZE)
 }
 
@@ -51,6 +52,7 @@ pub unsafe extern "C" fn free(ptr: *mut u8) {
         Err(_) => return,
     };
 
+    crate::memory::record_c_free(size);
     dealloc(header_ptr as *mut u8, layout);
 }
 
@@ -0,0 +1,326 @@
+use crate::errors::CompressorError;
+use crate::CompressOptions;
+use std::path::Path;
+
+/// 目录中自动查找的配置文件名，按顺序取第一个存在的
+pub const FILE_NAMES: [&str; 2] = ["tinylocal.toml", "tinylocal.json"];
+
+/// tinylocal.toml / tinylocal.json：顶层为默认设置，[[rule]] 按顺序匹配，第一条命中的规则生效。
+///
+/// ```toml
+/// format = "auto"
+/// quality = 75
+///
+/// [[rule]]
+/// glob = "assets/icons/*.png"
+/// preset = "lossless"
+///
+/// [[rule]]
+/// glob = "photos/**"
+/// format = "avif"
+/// quality = 60
+/// resize_mode = "maxWidth"
+/// resize_value = 2048
+/// ```
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct Config {
+    #[serde(flatten)]
+    pub defaults: Rule,
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>,
+}
+
+/// 一条规则。命中时以顶层设置为基础，依次叠加 preset 与本规则中写出的字段
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct Rule {
+    /// 相对于输入目录的路径模式：`*` 与 `?` 不跨目录，`**` 匹配任意层目录
+    pub glob: Option<String>,
+    pub preset: Option<String>,
+    pub format: Option<String>,
+    pub quality: Option<u8>,
+    #[serde(flatten)]
+    pub options: CompressOptions,
+}
+
+/// 某个文件最终使用的设置
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub format: String,
+    pub quality: u8,
+    pub options: CompressOptions,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            format: "auto".to_string(),
+            quality: 75,
+            options: CompressOptions::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_toml(text: &str) -> Result<Self, CompressorError> {
+        let table: toml::Table = toml::from_str(text).map_err(|err| invalid(err.to_string()))?;
+        Self::from_value(serde_json::to_value(table).map_err(|err| invalid(err.to_string()))?)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, CompressorError> {
+        Self::from_value(serde_json::from_str(text).map_err(|err| invalid(err.to_string()))?)
+    }
+
+    /// 两种格式都先转为 JSON 值，检查拼错的键与预设名后再反序列化
+    fn from_value(value: serde_json::Value) -> Result<Self, CompressorError> {
+        check_keys(&value, true)?;
+        if let Some(rules) = value.get("rule").and_then(|rules| rules.as_array()) {
+            for rule in rules {
+                check_keys(rule, false)?;
+            }
+        }
+        let config: Self = serde_json::from_value(value).map_err(|err| invalid(err.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 按扩展名选择格式，.json 以外都按 TOML 解析
+    pub fn load(path: &Path) -> Result<Self, CompressorError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| invalid(format!("{}: {err}", path.display())))?;
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        }
+    }
+
+    /// 读取目录中的 tinylocal.toml 或 tinylocal.json，都不存在时返回 None
+    pub fn discover(dir: &Path) -> Result<Option<Self>, CompressorError> {
+        FILE_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+            .map(|path| Self::load(&path))
+            .transpose()
+    }
+
+    /// 检查规则中的预设名，避免处理到一半才报错
+    pub fn validate(&self) -> Result<(), CompressorError> {
+        for rule in std::iter::once(&self.defaults).chain(&self.rules) {
+            if let Some(preset) = &rule.preset {
+                if crate::presets::find(preset).is_none() {
+                    return Err(invalid(format!("Unknown preset: {preset}")));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// relative 为相对于输入目录、以 / 分隔的路径；第一条命中的规则叠加在顶层设置之上
+    pub fn resolve(&self, relative: &str) -> Settings {
+        let defaults = self.defaults.apply(Settings::default());
+        let rule = self.rules.iter().find(|rule| {
+            rule.glob
+                .as_deref()
+                .is_some_and(|glob| glob_match(glob.as_bytes(), relative.as_bytes()))
+        });
+        match rule {
+            Some(rule) => rule.apply(defaults),
+            None => defaults,
+        }
+    }
+}
+
+impl Rule {
+    /// 先叠加预设，再叠加本规则中设置了的字段；没写的字段保留 base 的值
+    fn apply(&self, base: Settings) -> Settings {
+        let mut settings = base;
+        if let Some(preset) = self.preset.as_deref().and_then(crate::presets::find) {
+            settings.format = preset.format.to_string();
+            settings.quality = preset.quality;
+            settings.options = overlay(&settings.options, &preset.options);
+        }
+        if let Some(format) = &self.format {
+            settings.format = format.clone();
+        }
+        if let Some(quality) = self.quality {
+            settings.quality = quality;
+        }
+        settings.options = overlay(&settings.options, &self.options);
+        settings
+    }
+}
+
+/// 字段逐个合并：top 中不为 None 的字段覆盖 base
+fn overlay(base: &CompressOptions, top: &CompressOptions) -> CompressOptions {
+    let mut merged = serde_json::to_value(base).expect("CompressOptions serializes to JSON");
+    let top = serde_json::to_value(top).expect("CompressOptions serializes to JSON");
+    if let (Some(merged), Some(top)) = (merged.as_object_mut(), top.as_object()) {
+        for (key, value) in top.iter().filter(|(_, value)| !value.is_null()) {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    serde_json::from_value(merged).expect("merged options deserialize")
+}
+
+/// serde 的 flatten 不支持 deny_unknown_fields，这里按 CompressOptions 的字段名检查未知键
+fn check_keys(value: &serde_json::Value, top_level: bool) -> Result<(), CompressorError> {
+    let Some(table) = value.as_object() else {
+        return Ok(());
+    };
+    let options = serde_json::to_value(CompressOptions::default())
+        .expect("CompressOptions serializes to JSON");
+    let known = |key: &str| {
+        matches!(key, "glob" | "preset" | "format" | "quality")
+            || (top_level && key == "rule")
+            || options.get(key).is_some()
+    };
+    match table.keys().find(|key| !known(key)) {
+        Some(key) => Err(invalid(format!("unknown key \"{key}\""))),
+        None => Ok(()),
+    }
+}
+
+fn invalid(message: String) -> CompressorError {
+    CompressorError::InvalidOption(format!("Invalid config: {message}"))
+}
+
+/// `*` 匹配目录内任意字符，`?` 匹配一个字符，`**` 匹配任意层目录（`**/` 可以匹配零层）
+fn glob_match(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            glob_match(rest, path)
+                || (1..=path.len()).any(|i| path[i - 1] == b'/' && glob_match(rest, &path[i..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| glob_match(rest, &path[i..])),
+        [b'*', rest @ ..] => {
+            for i in 0..=path.len() {
+                if glob_match(rest, &path[i..]) {
+                    return true;
+                }
+                if path.get(i) == Some(&b'/') {
+                    break;
+                }
+            }
+            false
+        }
+        [b'?', rest @ ..] => {
+            matches!(path.first(), Some(&c) if c != b'/') && glob_match(rest, &path[1..])
+        }
+        [c, rest @ ..] => path.first() == Some(c) && glob_match(rest, &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        glob_match(pattern.as_bytes(), path.as_bytes())
+    }
+
+    #[test]
+    fn double_star_slash_matches_zero_or_more_directories() {
+        assert!(matches("**/*.png", "a.png"));
+        assert!(matches("**/*.png", "icons/a.png"));
+        assert!(matches("photos/**/*.jpg", "photos/a.jpg"));
+        assert!(matches("photos/**/*.jpg", "photos/2024/05/a.jpg"));
+        assert!(matches("photos/**", "photos/2024/a.jpg"));
+        assert!(!matches("photos/**/*.jpg", "other/a.jpg"));
+    }
+
+    #[test]
+    fn single_star_and_question_mark_stay_in_one_directory() {
+        assert!(matches("*.png", "a.png"));
+        assert!(!matches("*.png", "icons/a.png"));
+        assert!(matches("icons/*.png", "icons/a.png"));
+        assert!(!matches("icons/*.png", "icons/small/a.png"));
+        assert!(matches("img?.png", "img1.png"));
+        assert!(!matches("img?.png", "img10.png"));
+        assert!(!matches("a?b", "a/b"));
+    }
+
+    #[test]
+    fn first_matching_rule_wins_and_inherits_defaults() {
+        let config = Config::from_toml(
+            r#"
+            quality = 60
+            max_pixels = 1000000
+
+            [[rule]]
+            glob = "photos/**"
+            format = "jpeg"
+
+            [[rule]]
+            glob = "**/*.jpg"
+            format = "webp"
+            quality = 90
+            "#,
+        )
+        .unwrap();
+
+        let photo = config.resolve("photos/a.jpg");
+        assert_eq!(photo.format, "jpeg");
+        assert_eq!(photo.quality, 60);
+        assert_eq!(photo.options.max_pixels, Some(1_000_000));
+
+        let other = config.resolve("misc/a.jpg");
+        assert_eq!(other.format, "webp");
+        assert_eq!(other.quality, 90);
+        assert_eq!(other.options.max_pixels, Some(1_000_000));
+
+        let unmatched = config.resolve("a.png");
+        assert_eq!(unmatched.format, "auto");
+        assert_eq!(unmatched.quality, 60);
+    }
+
+    #[test]
+    fn preset_rule_keeps_its_own_options_in_toml_and_json() {
+        let toml = Config::from_toml(
+            r#"
+            [[rule]]
+            glob = "icons/*.png"
+            preset = "lossless"
+            resize_mode = "maxWidth"
+            resize_value = 512
+            "#,
+        )
+        .unwrap();
+        let json = Config::from_json(
+            r#"{
+                "rule": [{
+                    "glob": "icons/*.png",
+                    "preset": "lossless",
+                    "resize_mode": "maxWidth",
+                    "resize_value": 512
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        for config in [toml, json] {
+            let settings = config.resolve("icons/logo.png");
+            assert_eq!(settings.format, "png");
+            assert_eq!(settings.quality, 100);
+            assert_eq!(settings.options.png_truecolor, Some(true));
+            assert_eq!(settings.options.resize_mode.as_deref(), Some("maxWidth"));
+            assert_eq!(settings.options.resize_value, Some(512));
+        }
+    }
+
+    #[test]
+    fn unknown_presets_and_keys_are_rejected() {
+        let preset = Config::from_toml("[[rule]]\nglob = \"*\"\npreset = \"tpyo\"\n");
+        assert!(matches!(preset, Err(CompressorError::InvalidOption(_))));
+
+        let key = Config::from_toml("qualty = 60\n");
+        assert!(matches!(key, Err(CompressorError::InvalidOption(_))));
+
+        let rule_key = Config::from_json(r#"{"rule": [{"glob": "*", "fromat": "png"}]}"#);
+        assert!(matches!(rule_key, Err(CompressorError::InvalidOption(_))));
+    }
+}
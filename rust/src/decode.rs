@@ -0,0 +1,223 @@
+use crate::errors::CompressorError;
+use crate::utils::detect_format;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::io::Cursor;
+
+/// 解码资源上限，防止解压炸弹耗尽 wasm 内存
+#[derive(Debug, Clone, Copy)]
+pub struct DecodeLimits {
+    pub max_width: u32,
+    pub max_height: u32,
+    /// 宽 × 高
+    pub max_pixels: u64,
+    /// 单次解码允许分配的字节数
+    pub max_alloc: u64,
+}
+
+impl DecodeLimits {
+    /// 约 1 亿像素、512 MiB，足够容纳手机相机原图
+    pub const DEFAULT: DecodeLimits = DecodeLimits {
+        max_width: 32768,
+        max_height: 32768,
+        max_pixels: 100_000_000,
+        max_alloc: 512 * 1024 * 1024,
+    };
+}
+
+thread_local! {
+    // 当前任务的上限，只在 with_limits 的作用域内生效；并行的任务各自使用自己的上限
+    static LIMITS: Cell<DecodeLimits> = const { Cell::new(DecodeLimits::DEFAULT) };
+}
+
+/// 在 f 执行期间使用 limits，结束后恢复之前的上限
+pub fn with_limits<R>(limits: DecodeLimits, f: impl FnOnce() -> R) -> R {
+    struct Restore(DecodeLimits);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            LIMITS.with(|current| current.set(self.0));
+        }
+    }
+
+    let _restore = Restore(LIMITS.with(|current| current.replace(limits)));
+    f()
+}
+
+/// 当前线程生效的上限，不在 with_limits 作用域内时为默认值
+pub fn limits() -> DecodeLimits {
+    LIMITS.with(Cell::get)
+}
+
+/// 在分配像素缓冲区之前检查尺寸
+pub fn check_dimensions(width: u32, height: u32) -> Result<(), CompressorError> {
+    let limits = limits();
+    if width > limits.max_width || height > limits.max_height {
+        return Err(CompressorError::LimitExceeded(format!(
+            "image is {width}x{height}, maximum is {}x{}",
+            limits.max_width, limits.max_height
+        )));
+    }
+    let pixels = u64::from(width) * u64::from(height);
+    if pixels > limits.max_pixels {
+        return Err(CompressorError::LimitExceeded(format!(
+            "image has {pixels} pixels, maximum is {}",
+            limits.max_pixels
+        )));
+    }
+    Ok(())
+}
+
+/// 检查累计分配（例如动图的全部帧）
+pub fn check_alloc(bytes: u64) -> Result<(), CompressorError> {
+    let max_alloc = limits().max_alloc;
+    if bytes > max_alloc {
+        return Err(CompressorError::LimitExceeded(format!(
+            "decoding needs {bytes} bytes, maximum is {max_alloc}"
+        )));
+    }
+    Ok(())
+}
+
+/// image 库解码：先只读文件头检查尺寸，再带上限解码
+fn load_with_limits(
+    data: &[u8],
+    format: Option<image::ImageFormat>,
+) -> Result<image::DynamicImage, CompressorError> {
+    let reader = || -> Result<_, CompressorError> {
+        let reader = image::io::Reader::new(Cursor::new(data));
+        Ok(match format {
+            Some(format) => {
+                let mut reader = reader;
+                reader.set_format(format);
+                reader
+            }
+            None => reader
+                .with_guessed_format()
+                .map_err(|err| CompressorError::DecodeError(err.to_string()))?,
+        })
+    };
+
+    let (width, height) = reader()?.into_dimensions()?;
+    check_dimensions(width, height)?;
+
+    let current = limits();
+    let mut image_limits = image::io::Limits::default();
+    image_limits.max_image_width = Some(current.max_width);
+    image_limits.max_image_height = Some(current.max_height);
+    image_limits.max_alloc = Some(current.max_alloc);
+
+    let mut reader = reader()?;
+    reader.limits(image_limits);
+    Ok(reader.decode()?)
+}
+
+/// 解码输入图片，image 库不支持的格式在这里分发到对应解码器
+pub fn load_image(data: &[u8]) -> Result<image::DynamicImage, CompressorError> {
+    crate::progress::report("decode", 0.0)?;
+    let image = match detect_format(data) {
+        Some("heic") => load_heif(data),
+        Some("avif") => load_avif(data),
+        Some("jxl") => load_jxl(data),
+        Some("ico") => load_ico(data),
+        Some("svg") => load_svg(data, 0),
+        _ => load_with_limits(data, None),
+    }?;
+    crate::progress::report("decode", 1.0)?;
+    Ok(image)
+}
+
+/// ICO / CUR 取尺寸最大的一项。内嵌 PNG 直接解码（image 库只接受 RGBA 的内嵌 PNG），
+/// BMP 项交给 image 库处理；CUR 与 ICO 结构相同，但 image 库只按 ICO 的签名识别
+fn load_ico(data: &[u8]) -> Result<image::DynamicImage, CompressorError> {
+    let count = u16::from_le_bytes([data[4], data[5]]) as usize;
+    let best = data[6..]
+        .chunks_exact(16)
+        .take(count)
+        .max_by_key(|entry| {
+            // 宽高为 0 表示 256
+            let width = if entry[0] == 0 { 256 } else { u32::from(entry[0]) };
+            let height = if entry[1] == 0 { 256 } else { u32::from(entry[1]) };
+            (width * height, u16::from_le_bytes([entry[6], entry[7]]))
+        });
+
+    if let Some(entry) = best {
+        let len = u32::from_le_bytes([entry[8], entry[9], entry[10], entry[11]]) as usize;
+        let offset = u32::from_le_bytes([entry[12], entry[13], entry[14], entry[15]]) as usize;
+        if let Some(image) = data.get(offset..offset.saturating_add(len)) {
+            if detect_format(image) == Some("png") {
+                return load_with_limits(image, Some(image::ImageFormat::Png));
+            }
+        }
+    }
+
+    load_with_limits(data, Some(image::ImageFormat::Ico))
+}
+
+/// 从多页 TIFF 中取出指定页，重新封装为无损的单页 TIFF，之后按普通图片处理
+pub fn select_page(data: &[u8], page: u32) -> Result<Cow<'_, [u8]>, CompressorError> {
+    if page == 0 || detect_format(data) != Some("tiff") {
+        return Ok(Cow::Borrowed(data));
+    }
+
+    let image = crate::tiff::decode_page(data, page)?;
+    let mut out = Cursor::new(Vec::new());
+    image
+        .write_to(&mut out, image::ImageOutputFormat::Tiff)
+        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+    Ok(Cow::Owned(out.into_inner()))
+}
+
+/// 解码并按 EXIF 方向旋转
+pub fn load_oriented(data: &[u8], auto_rotate: bool) -> Result<image::DynamicImage, CompressorError> {
+    let image = load_image(data)?;
+    // HEIF 的 irot/imir 变换由 libheif 在解码时完成，EXIF 方向仅供参考，不能再旋转一次
+    if !auto_rotate || detect_format(data) == Some("heic") {
+        return Ok(image);
+    }
+
+    match crate::exif::get_exif_orientation(data) {
+        Ok(orientation) => Ok(crate::exif::apply_exif_rotation(image, orientation)),
+        Err(_) => Ok(image),
+    }
+}
+
+#[cfg(feature = "heif")]
+fn load_heif(data: &[u8]) -> Result<image::DynamicImage, CompressorError> {
+    crate::heif::decode_heif(data)
+}
+
+#[cfg(not(feature = "heif"))]
+fn load_heif(_data: &[u8]) -> Result<image::DynamicImage, CompressorError> {
+    Err(CompressorError::HeifNotEnabled)
+}
+
+#[cfg(all(feature = "avif-decode", not(target_arch = "wasm32")))]
+fn load_avif(data: &[u8]) -> Result<image::DynamicImage, CompressorError> {
+    crate::avif_decode::decode_avif(data)
+}
+
+#[cfg(not(all(feature = "avif-decode", not(target_arch = "wasm32"))))]
+fn load_avif(_data: &[u8]) -> Result<image::DynamicImage, CompressorError> {
+    Err(CompressorError::AvifDecodeNotEnabled)
+}
+
+#[cfg(feature = "jxl")]
+fn load_jxl(data: &[u8]) -> Result<image::DynamicImage, CompressorError> {
+    crate::jxl::decode_jxl(data)
+}
+
+#[cfg(not(feature = "jxl"))]
+fn load_jxl(_data: &[u8]) -> Result<image::DynamicImage, CompressorError> {
+    Err(CompressorError::JxlNotEnabled)
+}
+
+/// 栅格化 SVG，width 为 0 时使用 SVG 自身的尺寸
+#[cfg(feature = "svg-raster")]
+pub fn load_svg(data: &[u8], width: u32) -> Result<image::DynamicImage, CompressorError> {
+    crate::svg::rasterize(data, width)
+}
+
+#[cfg(not(feature = "svg-raster"))]
+pub fn load_svg(_data: &[u8], _width: u32) -> Result<image::DynamicImage, CompressorError> {
+    Err(CompressorError::SvgRasterNotEnabled)
+}
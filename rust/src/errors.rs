@@ -10,13 +10,32 @@ pub enum CompressorError {
     EncodeError(String),
     #[error("WebP feature not enabled")]
     WebpNotEnabled,
+    #[error("HEIF feature not enabled")]
+    HeifNotEnabled,
+    #[error("AVIF decode feature not enabled")]
+    AvifDecodeNotEnabled,
+    #[error("JPEG XL feature not enabled")]
+    JxlNotEnabled,
+    #[error("JPEG XL encode feature not enabled")]
+    JxlEncodeNotEnabled,
+    #[error("SVG rasterization feature not enabled")]
+    SvgRasterNotEnabled,
+    #[error("Limit exceeded: {0}")]
+    LimitExceeded(String),
+    #[error("Invalid option: {0}")]
+    InvalidOption(String),
     #[error("EXIF error: {0}")]
     ExifError(String),
+    #[error("Cancelled")]
+    Cancelled,
 }
 
 impl From<image::ImageError> for CompressorError {
     fn from(err: image::ImageError) -> Self {
-        CompressorError::DecodeError(err.to_string())
+        match err {
+            image::ImageError::Limits(err) => CompressorError::LimitExceeded(err.to_string()),
+            err => CompressorError::DecodeError(err.to_string()),
+        }
     }
 }
 
@@ -26,9 +45,44 @@ impl From<png::EncodingError> for CompressorError {
     }
 }
 
+impl From<png::DecodingError> for CompressorError {
+    fn from(err: png::DecodingError) -> Self {
+        CompressorError::DecodeError(err.to_string())
+    }
+}
+
+impl From<tiff::TiffError> for CompressorError {
+    fn from(err: tiff::TiffError) -> Self {
+        CompressorError::DecodeError(err.to_string())
+    }
+}
+
+impl From<imagequant::Error> for CompressorError {
+    fn from(err: imagequant::Error) -> Self {
+        match err {
+            // 进度回调要求中止
+            imagequant::Error::Aborted => CompressorError::Cancelled,
+            err => CompressorError::EncodeError(err.to_string()),
+        }
+    }
+}
+
+impl From<gif::EncodingError> for CompressorError {
+    fn from(err: gif::EncodingError) -> Self {
+        CompressorError::EncodeError(err.to_string())
+    }
+}
+
 #[cfg(feature = "webp")]
 impl From<webp::WebPEncodingError> for CompressorError {
     fn from(err: webp::WebPEncodingError) -> Self {
         CompressorError::EncodeError(format!("{err:?}"))
     }
 }
+
+#[cfg(feature = "heif")]
+impl From<libheif_rs::HeifError> for CompressorError {
+    fn from(err: libheif_rs::HeifError) -> Self {
+        CompressorError::DecodeError(err.to_string())
+    }
+}
@@ -36,6 +36,7 @@ pub fn apply_exif_rotation(image: image::DynamicImage, orientation: u32) -> imag
 }
 
 /// 检查是否包含EXIF数据
+#[allow(dead_code)]
 pub fn has_exif(data: &[u8]) -> bool {
     let mut cursor = Cursor::new(data);
     let mut reader = BufReader::new(&mut cursor);
@@ -0,0 +1,389 @@
+use crate::animation::{decode_animation, Animation};
+use crate::errors::CompressorError;
+use crate::resize::ResizeOptions;
+use std::borrow::Cow;
+
+/// 帧差优化后待写入的一帧
+struct OptimizedFrame {
+    left: u32,
+    top: u32,
+    image: image::RgbaImage,
+    delay_ms: u32,
+    dispose: gif::DisposalMethod,
+}
+
+/// 量化后的单帧：RGB 调色板、像素索引和透明索引
+struct QuantizedFrame {
+    palette: Vec<u8>,
+    indices: Vec<u8>,
+    transparent: Option<u8>,
+}
+
+pub fn compress_gif(
+    data: &[u8],
+    quality: u8,
+    dithering: bool,
+    resize: &ResizeOptions,
+) -> Result<Vec<u8>, CompressorError> {
+    let animation = decode_animation(data)?.resize(resize)?;
+    encode_gif(&animation, quality, dithering)
+}
+
+pub fn encode_gif(
+    animation: &Animation,
+    quality: u8,
+    dithering: bool,
+) -> Result<Vec<u8>, CompressorError> {
+    let width = u16::try_from(animation.width)
+        .map_err(|_| CompressorError::EncodeError("GIF width exceeds 65535".to_string()))?;
+    let height = u16::try_from(animation.height)
+        .map_err(|_| CompressorError::EncodeError("GIF height exceeds 65535".to_string()))?;
+
+    let mut out = Vec::new();
+    {
+        let mut encoder = gif::Encoder::new(&mut out, width, height, &[])?;
+        if animation.is_animated() {
+            match animation.loop_count {
+                0 => encoder.set_repeat(gif::Repeat::Infinite)?,
+                // 只播放一次时不写循环扩展块
+                1 => {}
+                count => {
+                    let repeat = u16::try_from(count - 1).unwrap_or(u16::MAX);
+                    encoder.set_repeat(gif::Repeat::Finite(repeat))?;
+                }
+            }
+        }
+
+        let frames = optimize_frames(animation);
+        for (i, frame) in frames.iter().enumerate() {
+            crate::progress::report("encode", i as f32 / frames.len() as f32)?;
+            let quantized = quantize_frame(&frame.image, quality, dithering)?;
+            let gif_frame = gif::Frame {
+                delay: u16::try_from(frame.delay_ms / 10).unwrap_or(u16::MAX),
+                dispose: frame.dispose,
+                transparent: quantized.transparent,
+                left: frame.left as u16,
+                top: frame.top as u16,
+                width: frame.image.width() as u16,
+                height: frame.image.height() as u16,
+                palette: Some(quantized.palette),
+                buffer: Cow::Owned(quantized.indices),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&gif_frame)?;
+        }
+    }
+
+    Ok(out)
+}
+
+/// GIF 只有 1 位透明度
+fn visible(pixel: &image::Rgba<u8>) -> bool {
+    pixel[3] >= 128
+}
+
+/// 与上一帧相比是否需要重绘该像素
+fn pixel_changed(current: &image::Rgba<u8>, previous: Option<&image::Rgba<u8>>) -> bool {
+    match previous {
+        Some(previous) => {
+            visible(current) != visible(previous)
+                || (visible(current) && current.0[..3] != previous.0[..3])
+        }
+        None => visible(current),
+    }
+}
+
+/// 上一帧可见的像素在这一帧变为透明时，叠加绘制无法还原，需要先清空画布
+fn reveals_transparency(previous: &image::RgbaImage, current: &image::RgbaImage) -> bool {
+    previous
+        .pixels()
+        .zip(current.pixels())
+        .any(|(previous, current)| visible(previous) && !visible(current))
+}
+
+/// 帧差裁剪与透明优化：每帧只保留相对上一帧变化的矩形区域，未变化的像素写为透明
+fn optimize_frames(animation: &Animation) -> Vec<OptimizedFrame> {
+    let frames = &animation.frames;
+    let needs_clear: Vec<bool> = (0..frames.len())
+        .map(|i| i > 0 && reveals_transparency(&frames[i - 1].image, &frames[i].image))
+        .collect();
+
+    let mut out: Vec<OptimizedFrame> = Vec::with_capacity(frames.len());
+    for (i, frame) in frames.iter().enumerate() {
+        let previous = if i == 0 || needs_clear[i] {
+            None
+        } else {
+            Some(&frames[i - 1].image)
+        };
+        // 下一帧需要清空画布时，本帧必须覆盖整个画布并在显示后恢复为背景
+        let clear_after = needs_clear.get(i + 1).copied().unwrap_or(false);
+
+        let bounds = if clear_after {
+            Some((0, 0, animation.width, animation.height))
+        } else {
+            changed_bounds(&frame.image, previous)
+        };
+        let (left, top, width, height) = match bounds {
+            Some(bounds) => bounds,
+            None => match out.last_mut() {
+                // 与上一帧完全相同，合并延时
+                Some(last) if last.dispose == gif::DisposalMethod::Keep => {
+                    last.delay_ms += frame.delay_ms;
+                    continue;
+                }
+                _ => (0, 0, 1, 1),
+            },
+        };
+
+        let image = image::RgbaImage::from_fn(width, height, |x, y| {
+            let current = frame.image.get_pixel(left + x, top + y);
+            let before = previous.map(|previous| previous.get_pixel(left + x, top + y));
+            if pixel_changed(current, before) {
+                image::Rgba([current[0], current[1], current[2], 255])
+            } else {
+                image::Rgba([0, 0, 0, 0])
+            }
+        });
+
+        out.push(OptimizedFrame {
+            left,
+            top,
+            image,
+            delay_ms: frame.delay_ms,
+            dispose: if clear_after {
+                gif::DisposalMethod::Background
+            } else {
+                gif::DisposalMethod::Keep
+            },
+        });
+    }
+
+    out
+}
+
+fn changed_bounds(
+    current: &image::RgbaImage,
+    previous: Option<&image::RgbaImage>,
+) -> Option<(u32, u32, u32, u32)> {
+    let (mut min_x, mut min_y) = (u32::MAX, u32::MAX);
+    let (mut max_x, mut max_y) = (0, 0);
+    for (x, y, pixel) in current.enumerate_pixels() {
+        if pixel_changed(pixel, previous.map(|previous| previous.get_pixel(x, y))) {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+    if min_x == u32::MAX {
+        return None;
+    }
+    Some((min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+}
+
+/// 使用 imagequant 为单帧生成局部调色板
+fn quantize_frame(
+    image: &image::RgbaImage,
+    quality: u8,
+    dithering: bool,
+) -> Result<QuantizedFrame, CompressorError> {
+    let (width, height) = image.dimensions();
+
+    let mut attr = imagequant::new();
+    // 最低质量设为 0，避免个别帧达不到质量下限导致整个动图失败
+    attr.set_quality(0, quality)
+        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+    attr.set_speed(3)
+        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+
+    let mut img = attr
+        .new_image_borrowed(
+            crate::utils::as_rgba_pixels(image),
+            width as usize,
+            height as usize,
+            0.0,
+        )
+        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+
+    let mut res = attr.quantize(&mut img)?;
+
+    res.set_dithering_level(if dithering { 1.0 } else { 0.0 })
+        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+
+    let (palette, mut indices) = res.remapped(&mut img)?;
+
+    // GIF 只支持一个透明索引，把所有透明颜色合并到第一个
+    let transparent = palette.iter().position(|color| color.a < 128);
+    if let Some(transparent) = transparent {
+        for index in indices.iter_mut() {
+            if palette[*index as usize].a < 128 {
+                *index = transparent as u8;
+            }
+        }
+    }
+
+    Ok(QuantizedFrame {
+        palette: palette
+            .iter()
+            .flat_map(|color| [color.r, color.g, color.b])
+            .collect(),
+        indices,
+        transparent: transparent.map(|index| index as u8),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::Frame;
+
+    const RED: image::Rgba<u8> = image::Rgba([200, 30, 30, 255]);
+    const BLUE: image::Rgba<u8> = image::Rgba([20, 40, 220, 255]);
+    const GREEN: image::Rgba<u8> = image::Rgba([30, 180, 60, 255]);
+    const CLEAR: image::Rgba<u8> = image::Rgba([0, 0, 0, 0]);
+
+    /// 红底上画一个 4×4 的蓝块
+    fn square(left: u32, top: u32) -> image::RgbaImage {
+        image::RgbaImage::from_fn(16, 12, |x, y| {
+            if (left..left + 4).contains(&x) && (top..top + 4).contains(&y) {
+                BLUE
+            } else {
+                RED
+            }
+        })
+    }
+
+    /// 第 3 帧与第 2 帧相同，第 5 帧把左侧变为透明
+    fn animation(loop_count: u32) -> Animation {
+        let moved = square(8, 4);
+        let mut striped = moved.clone();
+        for x in 0..16 {
+            striped.put_pixel(x, 10, GREEN);
+        }
+        let mut cleared = striped.clone();
+        for (x, _, pixel) in cleared.enumerate_pixels_mut() {
+            if x < 4 {
+                *pixel = CLEAR;
+            }
+        }
+        let mut last = cleared.clone();
+        last.put_pixel(12, 1, GREEN);
+
+        let frames = [
+            (square(2, 2), 100),
+            (moved.clone(), 50),
+            (moved, 70),
+            (striped, 40),
+            (cleared, 90),
+            (last, 60),
+        ];
+        Animation {
+            width: 16,
+            height: 12,
+            loop_count,
+            frames: frames
+                .into_iter()
+                .map(|(image, delay_ms)| Frame { image, delay_ms })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn optimize_frames_crops_merges_and_clears() {
+        let frames = optimize_frames(&animation(0));
+        let layout: Vec<_> = frames
+            .iter()
+            .map(|frame| {
+                (
+                    frame.left,
+                    frame.top,
+                    frame.image.width(),
+                    frame.image.height(),
+                    frame.delay_ms,
+                    frame.dispose,
+                )
+            })
+            .collect();
+        use gif::DisposalMethod::{Background, Keep};
+        assert_eq!(
+            layout,
+            [
+                (0, 0, 16, 12, 100, Keep),
+                // 覆盖蓝块移动前后的位置，相同的下一帧并入延时
+                (2, 2, 10, 6, 120, Keep),
+                // 下一帧出现透明，本帧覆盖整个画布并恢复为背景
+                (0, 0, 16, 12, 40, Background),
+                // 画布已清空，只需绘制可见的部分
+                (4, 0, 12, 12, 90, Keep),
+                (12, 1, 1, 1, 60, Keep),
+            ]
+        );
+        // 裁剪区域内未变化的像素写为透明
+        assert_eq!(*frames[1].image.get_pixel(0, 5), CLEAR);
+        assert_eq!(*frames[1].image.get_pixel(0, 0), RED);
+        assert_eq!(*frames[1].image.get_pixel(6, 2), BLUE);
+        assert_eq!(*frames[1].image.get_pixel(0, 1), RED);
+    }
+
+    #[test]
+    fn encoded_frames_composite_to_the_input() {
+        let input = animation(3);
+        let output = encode_gif(&input, 100, false).unwrap();
+        let decoded = decode_animation(&output).unwrap();
+        assert_eq!(decoded.loop_count, 3);
+
+        // 第 3 帧已并入第 2 帧
+        let expected: Vec<&Frame> = input
+            .frames
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 2)
+            .map(|(_, frame)| frame)
+            .collect();
+        let delays: Vec<u32> = decoded.frames.iter().map(|frame| frame.delay_ms).collect();
+        assert_eq!(delays, [100, 120, 40, 90, 60]);
+        for (i, (expected, actual)) in expected.iter().zip(&decoded.frames).enumerate() {
+            for (x, y, pixel) in expected.image.enumerate_pixels() {
+                let actual = actual.image.get_pixel(x, y);
+                if visible(pixel) {
+                    assert_eq!(pixel, actual, "frame {i} at {x},{y}");
+                } else {
+                    assert!(!visible(actual), "frame {i} at {x},{y}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn loop_count_is_preserved() {
+        for loop_count in [0, 1, 5] {
+            let output = encode_gif(&animation(loop_count), 80, true).unwrap();
+            assert_eq!(decode_animation(&output).unwrap().loop_count, loop_count);
+        }
+    }
+
+    #[test]
+    fn transparent_colors_share_one_palette_entry() {
+        let transparent = [
+            image::Rgba([255, 0, 0, 0]),
+            image::Rgba([0, 255, 0, 100]),
+            image::Rgba([0, 0, 255, 20]),
+        ];
+        let image = image::RgbaImage::from_fn(12, 12, |x, y| match (x + y) % 5 {
+            0..=2 => transparent[((x + y) % 5) as usize],
+            3 => RED,
+            _ => BLUE,
+        });
+        let quantized = quantize_frame(&image, 100, false).unwrap();
+        let index = quantized.transparent.expect("transparent index");
+        for (pixel, &actual) in image.pixels().zip(&quantized.indices) {
+            if visible(pixel) {
+                assert_ne!(actual, index);
+                let rgb = &quantized.palette[actual as usize * 3..actual as usize * 3 + 3];
+                assert_eq!(rgb, &pixel.0[..3]);
+            } else {
+                assert_eq!(actual, index);
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use crate::errors::CompressorError;
+use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+/// 解码 HEIC/HEIF 主图像
+/// 输出不保留色彩配置文件，因此带 ICC（iPhone 通常为 Display P3）时转换到 sRGB
+pub fn decode_heif(data: &[u8]) -> Result<image::DynamicImage, CompressorError> {
+    let lib_heif = LibHeif::new();
+    let context = HeifContext::read_from_bytes(data)?;
+    let handle = context.primary_image_handle()?;
+    crate::decode::check_dimensions(handle.width(), handle.height())?;
+    let image = lib_heif.decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), None)?;
+
+    let planes = image.planes();
+    let plane = planes
+        .interleaved
+        .ok_or_else(|| CompressorError::DecodeError("HEIF image has no RGBA plane".to_string()))?;
+
+    // 去掉行尾对齐填充
+    let row_len = plane.width as usize * 4;
+    let mut rgba = Vec::with_capacity(row_len * plane.height as usize);
+    for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+        rgba.extend_from_slice(&row[..row_len]);
+    }
+
+    if let Some(profile) = handle.color_profile_raw() {
+        crate::icc::to_srgb(&mut rgba, &profile.data);
+    }
+
+    let buffer = image::RgbaImage::from_raw(plane.width, plane.height, rgba)
+        .ok_or_else(|| CompressorError::DecodeError("Invalid HEIF image size".to_string()))?;
+    Ok(image::DynamicImage::ImageRgba8(buffer))
+}
@@ -0,0 +1,19 @@
+/// 按嵌入的 ICC 配置文件把 RGBA 像素转换到 sRGB
+/// ICC 无法解析时保留原始像素
+pub fn to_srgb(rgba: &mut [u8], icc: &[u8]) {
+    let Ok(source) = moxcms::ColorProfile::new_from_slice(icc) else {
+        return;
+    };
+    let target = moxcms::ColorProfile::new_srgb();
+    let Ok(transform) = source.create_transform_8bit(
+        moxcms::Layout::Rgba,
+        &target,
+        moxcms::Layout::Rgba,
+        moxcms::TransformOptions::default(),
+    ) else {
+        return;
+    };
+
+    let original = rgba.to_vec();
+    let _ = transform.transform(&original, rgba);
+}
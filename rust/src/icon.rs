@@ -0,0 +1,217 @@
+use crate::alpha::Background;
+use crate::errors::CompressorError;
+use crate::resize::{apply_resize, ResizeOptions};
+
+/// favicon.ico 包含的尺寸
+pub const ICO_SIZES: [u32; 6] = [16, 32, 48, 64, 128, 256];
+
+/// 图标集中的一个文件
+pub struct Icon {
+    pub name: &'static str,
+    pub data: Vec<u8>,
+}
+
+pub fn compress_ico(
+    data: &[u8],
+    quality: u8,
+    dithering: bool,
+    auto_rotate: bool,
+) -> Result<Vec<u8>, CompressorError> {
+    let image = crate::decode::load_oriented(data, auto_rotate)?;
+    encode_ico(&square(&image), quality, dithering)
+}
+
+/// 生成 favicon.ico、apple-touch-icon 以及 PWA manifest 常用的 192/512 图标
+pub fn generate_icon_set(
+    data: &[u8],
+    quality: u8,
+    dithering: bool,
+    auto_rotate: bool,
+) -> Result<Vec<Icon>, CompressorError> {
+    let image = crate::decode::load_oriented(data, auto_rotate)?;
+    let image = square(&image);
+
+    // iOS 会把透明区域渲染成黑色，apple-touch-icon 铺白底
+    let touch = image::DynamicImage::ImageRgb8(
+        crate::alpha::flatten(&resize_to(&image, 180)?, Background::default()).into_owned(),
+    );
+
+    Ok(vec![
+        Icon {
+            name: "favicon.ico",
+            data: encode_ico(&image, quality, dithering)?,
+        },
+        Icon {
+            name: "apple-touch-icon.png",
+            data: crate::png::encode_png(&touch, quality, dithering, false)?,
+        },
+        Icon {
+            name: "icon-192.png",
+            data: crate::png::encode_png(&resize_to(&image, 192)?, quality, dithering, false)?,
+        },
+        Icon {
+            name: "icon-512.png",
+            data: crate::png::encode_png(&resize_to(&image, 512)?, quality, dithering, false)?,
+        },
+    ])
+}
+
+/// 多尺寸 ICO：每个尺寸单独缩放并量化，以 PNG 形式存入目录项
+pub fn encode_ico(
+    image: &image::DynamicImage,
+    quality: u8,
+    dithering: bool,
+) -> Result<Vec<u8>, CompressorError> {
+    let entries = ICO_SIZES
+        .iter()
+        .map(|&size| {
+            let resized = resize_to(image, size)?;
+            Ok((
+                size,
+                crate::png::encode_png(&resized, quality, dithering, false)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, CompressorError>>()?;
+
+    // ICONDIR (6 字节) + 每项 ICONDIRENTRY (16 字节)，随后依次是各尺寸的图像数据
+    let mut out = Vec::new();
+    out.extend_from_slice(&[0, 0, 1, 0]);
+    out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+
+    let mut offset = 6 + 16 * entries.len();
+    for (size, png) in &entries {
+        // 宽高字段只有 1 字节，256 记为 0
+        let dimension = if *size >= 256 { 0 } else { *size as u8 };
+        out.extend_from_slice(&[dimension, dimension, 0, 0]);
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&bit_count(png).to_le_bytes());
+        out.extend_from_slice(&(png.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += png.len();
+    }
+    for (_, png) in entries {
+        out.extend_from_slice(&png);
+    }
+
+    Ok(out)
+}
+
+/// 按 PNG 的 IHDR 计算每像素位数，量化后的图标通常是调色板 PNG
+fn bit_count(png: &[u8]) -> u16 {
+    // 8 字节签名 + 8 字节块头 + 8 字节宽高，随后是位深与颜色类型
+    let (depth, color_type) = (u16::from(png[24]), png[25]);
+    let channels = match color_type {
+        2 => 3,
+        4 => 2,
+        6 => 4,
+        // 灰度与调色板每像素只有一个样本
+        _ => 1,
+    };
+    depth * channels
+}
+
+/// 缩放到指定边长；小图也必须放大，保证每个尺寸都存在
+fn resize_to(
+    image: &image::DynamicImage,
+    size: u32,
+) -> Result<image::DynamicImage, CompressorError> {
+    let options = ResizeOptions {
+        allow_upscale: true,
+        ..ResizeOptions::new("fixed", size)
+    };
+    apply_resize(image.clone(), &options)
+}
+
+/// 非正方形的源图居中放到透明的正方形画布上，避免图标被拉伸
+fn square(image: &image::DynamicImage) -> image::DynamicImage {
+    let (width, height) = (image.width(), image.height());
+    if width == height {
+        return image.clone();
+    }
+
+    let side = width.max(height);
+    let mut canvas = image::RgbaImage::new(side, side);
+    image::imageops::overlay(
+        &mut canvas,
+        &image.to_rgba8(),
+        i64::from((side - width) / 2),
+        i64::from((side - height) / 2),
+    );
+    image::DynamicImage::ImageRgba8(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_pads_with_transparent_borders() {
+        let image = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            4,
+            2,
+            image::Rgb([200, 10, 10]),
+        ));
+        let squared = square(&image).to_rgba8();
+        assert_eq!(squared.dimensions(), (4, 4));
+        for (_, y, pixel) in squared.enumerate_pixels() {
+            let expected = if (1..3).contains(&y) {
+                [200, 10, 10, 255]
+            } else {
+                [0, 0, 0, 0]
+            };
+            assert_eq!(pixel.0, expected, "row {y}");
+        }
+
+        // 竖图左右补边
+        let tall = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            1,
+            3,
+            image::Rgba([0, 0, 255, 255]),
+        ));
+        let squared = square(&tall).to_rgba8();
+        assert_eq!(squared.dimensions(), (3, 3));
+        assert_eq!(squared.get_pixel(0, 1)[3], 0);
+        assert_eq!(squared.get_pixel(1, 1).0, [0, 0, 255, 255]);
+        assert_eq!(squared.get_pixel(2, 1)[3], 0);
+    }
+
+    #[test]
+    fn ico_directory_describes_each_png() {
+        let image = image::DynamicImage::ImageRgba8(image::RgbaImage::from_fn(40, 40, |x, y| {
+            image::Rgba([
+                (x * 6) as u8,
+                (y * 6) as u8,
+                90,
+                if x < 20 { 255 } else { 128 },
+            ])
+        }));
+        let ico = encode_ico(&image, 75, false).unwrap();
+
+        let u16_at = |pos: usize| u16::from_le_bytes([ico[pos], ico[pos + 1]]);
+        let u32_at = |pos: usize| u32::from_le_bytes(ico[pos..pos + 4].try_into().unwrap());
+        // 保留字段 0，类型 1 表示图标
+        assert_eq!((u16_at(0), u16_at(2)), (0, 1));
+        assert_eq!(u16_at(4) as usize, ICO_SIZES.len());
+
+        let mut expected_offset = 6 + 16 * ICO_SIZES.len();
+        for (i, &size) in ICO_SIZES.iter().enumerate() {
+            let entry = 6 + 16 * i;
+            let dimension = if size == 256 { 0 } else { size as u8 };
+            assert_eq!(&ico[entry..entry + 4], &[dimension, dimension, 0, 0]);
+            assert_eq!(u16_at(entry + 4), 1);
+
+            let (len, offset) = (u32_at(entry + 8) as usize, u32_at(entry + 12) as usize);
+            assert_eq!(offset, expected_offset);
+            expected_offset += len;
+
+            let png = &ico[offset..offset + len];
+            let decoder = png::Decoder::new(png);
+            let reader = decoder.read_info().unwrap();
+            let info = reader.info();
+            assert_eq!((info.width, info.height), (size, size));
+            let bits = info.bit_depth as u16 * info.color_type.samples() as u16;
+            assert_eq!(u16_at(entry + 6), bits, "{size}px entry");
+        }
+        assert_eq!(expected_offset, ico.len());
+    }
+}
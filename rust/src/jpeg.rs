@@ -1,69 +1,46 @@
+use crate::alpha::Background;
 use crate::errors::CompressorError;
+use crate::resize::{apply_resize, ResizeOptions};
 
-fn apply_resize(image: image::DynamicImage, mode: &str, value: u32) -> image::DynamicImage {
-    match mode {
-        "percentage" => {
-            let scale = value as f32 / 100.0;
-            if scale >= 1.0 {
-                return image;
-            }
-            let new_width = (image.width() as f32 * scale).round() as u32;
-            let new_height = (image.height() as f32 * scale).round() as u32;
-            image.resize(new_width, new_height, image::imageops::FilterType::Lanczos3)
-        }
-        "maxWidth" => {
-            if image.width() <= value {
-                return image;
-            }
-            let scale = value as f32 / image.width() as f32;
-            let new_height = (image.height() as f32 * scale).round() as u32;
-            image.resize(value, new_height, image::imageops::FilterType::Lanczos3)
-        }
-        "maxHeight" => {
-            if image.height() <= value {
-                return image;
-            }
-            let scale = value as f32 / image.height() as f32;
-            let new_width = (image.width() as f32 * scale).round() as u32;
-            image.resize(new_width, value, image::imageops::FilterType::Lanczos3)
-        }
-        "fixed" => {
-            let aspect_ratio = image.height() as f32 / image.width() as f32;
-            let new_height = (value as f32 * aspect_ratio).round() as u32;
-            image.resize(value, new_height, image::imageops::FilterType::Lanczos3)
-        }
-        _ => image,
-    }
-}
-
-#[cfg(not(target_arch = "wasm32"))]
 pub fn compress_jpeg(
     data: &[u8],
     quality: u8,
     progressive: bool,
-    resize_mode: &str,
-    resize_value: u32,
+    resize: &ResizeOptions,
     auto_rotate: bool,
-    _strip_exif: bool, // mozjpeg 会自动清除 EXIF
+    _strip_exif: bool, // 重新编码会清除 EXIF 元数据
+    background: Background,
 ) -> Result<Vec<u8>, CompressorError> {
-    let mut image = image::load_from_memory(data)?;
-
-    // 应用 EXIF 自动旋转（如果启用）
-    if auto_rotate {
-        // 尝试从原始JPEG数据读取EXIF方向
-        if let Ok(orientation) = crate::exif::get_exif_orientation(data) {
-            image = crate::exif::apply_exif_rotation(image, orientation);
+    // 不缩放时逐行转码，大图不必整帧驻留内存。
+    // mozjpeg 的渐进式编码要缓存整幅图像的 DCT 系数，逐行写入省不了内存，改走整图路径；
+    // wasm 的 jpeg-encoder 只输出基线 JPEG，不受影响
+    let progressive_buffers = progressive && cfg!(not(target_arch = "wasm32"));
+    if resize.is_none() && !progressive_buffers {
+        if let Some(rows) = crate::stream::Rows::open(data, auto_rotate)? {
+            return crate::stream::encode_jpeg(rows, quality, background);
         }
     }
 
-    let image = apply_resize(image, resize_mode, resize_value);
-    let rgb = image.to_rgb8();
+    // 应用 EXIF 自动旋转（如果启用）
+    let image = crate::decode::load_oriented(data, auto_rotate)?;
+    let image = apply_resize(image, resize)?;
+    encode_jpeg(&image, quality, progressive, background)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn encode_jpeg(
+    image: &image::DynamicImage,
+    quality: u8,
+    progressive: bool,
+    background: Background,
+) -> Result<Vec<u8>, CompressorError> {
+    // JPEG 没有透明通道，先合成到背景上
+    let rgb = crate::alpha::flatten(image, background);
     let (width, height) = rgb.dimensions();
 
     let mut comp = mozjpeg::Compress::new(mozjpeg::ColorSpace::JCS_RGB);
     comp.set_size(width as usize, height as usize);
     comp.set_quality(quality as f32);
-    comp.set_mem_dest();
     if progressive {
         comp.set_progressive_mode();
     }
@@ -73,47 +50,36 @@ pub fn compress_jpeg(
         .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
 
     let row_stride = (width * 3) as usize;
-    let raw = rgb.into_raw();
 
-    for row in raw.chunks(row_stride) {
+    for (y, row) in rgb.as_raw().chunks(row_stride).enumerate() {
+        crate::progress::encode_row(y as u32, height)?;
         comp.write_scanlines(row)
             .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
     }
 
     let jpeg_data = comp
-        .finish_compress()
+        .finish()
         .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
     Ok(jpeg_data)
 }
 
 #[cfg(target_arch = "wasm32")]
-pub fn compress_jpeg(
-    data: &[u8],
+pub fn encode_jpeg(
+    image: &image::DynamicImage,
     quality: u8,
     _progressive: bool,
-    resize_mode: &str,
-    resize_value: u32,
-    auto_rotate: bool,
-    _strip_exif: bool, // wasm 重新编码会清除元数据
+    background: Background,
 ) -> Result<Vec<u8>, CompressorError> {
-    let mut image = image::load_from_memory(data)?;
-
-    // 应用 EXIF 自动旋转（如果启用）
-    if auto_rotate {
-        if let Ok(orientation) = crate::exif::get_exif_orientation(data) {
-            image = crate::exif::apply_exif_rotation(image, orientation);
-        }
-    }
-
-    let image = apply_resize(image, resize_mode, resize_value);
-    let rgb = image.to_rgb8();
+    let rgb = crate::alpha::flatten(image, background);
     let (width, height) = rgb.dimensions();
 
+    crate::progress::report("encode", 0.0)?;
     let mut out = Vec::new();
     let encoder = jpeg_encoder::Encoder::new(&mut out, quality);
     encoder
         .encode(rgb.as_raw(), width as u16, height as u16, jpeg_encoder::ColorType::Rgb)
         .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
 
+    crate::progress::report("encode", 1.0)?;
     Ok(out)
 }
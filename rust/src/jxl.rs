@@ -0,0 +1,202 @@
+use crate::errors::CompressorError;
+use crate::resize::{apply_resize, ResizeOptions};
+use crate::utils::detect_format;
+#[cfg(all(feature = "jxl-encode", not(target_arch = "wasm32")))]
+use jpegxl_sys::{
+    color::color_encoding::JxlColorEncoding,
+    common::types::{JxlDataType, JxlEndianness, JxlPixelFormat},
+    encoder::encode::*,
+    metadata::codestream_header::JxlBasicInfo,
+};
+use std::io::Cursor;
+#[cfg(all(feature = "jxl-encode", not(target_arch = "wasm32")))]
+use std::mem::MaybeUninit;
+#[cfg(all(feature = "jxl-encode", not(target_arch = "wasm32")))]
+use std::ptr;
+
+/// JPEG XL 编码
+/// mode 为 "lossless-jpeg" 时把 JPEG 无损转码为 JXL（可逐字节还原原 JPEG），否则按 quality 有损编码
+pub fn compress_jxl(
+    data: &[u8],
+    quality: u8,
+    mode: &str,
+    resize: &ResizeOptions,
+    auto_rotate: bool,
+) -> Result<Vec<u8>, CompressorError> {
+    if mode == "lossless-jpeg" {
+        // 转码直接复用 JPEG 的 DCT 系数，无法缩放或旋转像素
+        if detect_format(data) != Some("jpeg") || !resize.is_none() {
+            return Err(CompressorError::EncodeError(
+                "Lossless JPEG transcoding requires JPEG input without resizing".to_string(),
+            ));
+        }
+        return transcode_jpeg(data);
+    }
+
+    let image = crate::decode::load_oriented(data, auto_rotate)?;
+    let image = apply_resize(image, resize)?;
+    encode_jxl(&image, quality)
+}
+
+/// 解码 JPEG XL 首帧，输出转换到 sRGB
+pub fn decode_jxl(data: &[u8]) -> Result<image::DynamicImage, CompressorError> {
+    let mut image = jxl_oxide::JxlImage::builder()
+        .read(Cursor::new(data))
+        .map_err(|err| CompressorError::DecodeError(err.to_string()))?;
+    crate::decode::check_dimensions(image.width(), image.height())?;
+    image.request_color_encoding(jxl_oxide::EnumColourEncoding::srgb(
+        jxl_oxide::RenderingIntent::Relative,
+    ));
+
+    let render = image
+        .render_frame(0)
+        .map_err(|err| CompressorError::DecodeError(err.to_string()))?;
+    let mut stream = render.stream();
+    let (width, height) = (stream.width(), stream.height());
+    let mut buf = vec![0u8; width as usize * height as usize * stream.channels() as usize];
+    stream.write_to_buffer(&mut buf);
+
+    let image = match stream.channels() {
+        3 => image::RgbImage::from_raw(width, height, buf).map(image::DynamicImage::ImageRgb8),
+        4 => image::RgbaImage::from_raw(width, height, buf).map(image::DynamicImage::ImageRgba8),
+        _ => None,
+    };
+    image
+        .ok_or_else(|| CompressorError::DecodeError("Unsupported JPEG XL pixel format".to_string()))
+}
+
+/// libjxl 有损编码，quality 按 libjxl 的换算映射为 butteraugli 距离，100 为无损
+#[cfg(all(feature = "jxl-encode", not(target_arch = "wasm32")))]
+pub fn encode_jxl(image: &image::DynamicImage, quality: u8) -> Result<Vec<u8>, CompressorError> {
+    let has_alpha = crate::utils::has_transparency(image);
+    let pixels = if has_alpha {
+        image.to_rgba8().into_raw()
+    } else {
+        image.to_rgb8().into_raw()
+    };
+    let lossless = quality >= 100;
+
+    let encoder = Encoder::new()?;
+    unsafe {
+        let mut info = MaybeUninit::<JxlBasicInfo>::uninit();
+        JxlEncoderInitBasicInfo(info.as_mut_ptr());
+        let mut info = info.assume_init();
+        info.xsize = image.width();
+        info.ysize = image.height();
+        info.bits_per_sample = 8;
+        info.num_color_channels = 3;
+        info.uses_original_profile = lossless.into();
+        if has_alpha {
+            info.num_extra_channels = 1;
+            info.alpha_bits = 8;
+        }
+        check(JxlEncoderSetBasicInfo(encoder.0, &info))?;
+
+        let mut color = MaybeUninit::<JxlColorEncoding>::uninit();
+        JxlColorEncodingSetToSRGB(color.as_mut_ptr(), false.into());
+        check(JxlEncoderSetColorEncoding(encoder.0, color.as_ptr()))?;
+
+        let settings = JxlEncoderFrameSettingsCreate(encoder.0, ptr::null());
+        if lossless {
+            check(JxlEncoderSetFrameLossless(settings, true.into()))?;
+        } else {
+            let distance = JxlEncoderDistanceFromQuality(f32::from(quality));
+            check(JxlEncoderSetFrameDistance(settings, distance))?;
+        }
+
+        let format = JxlPixelFormat {
+            num_channels: if has_alpha { 4 } else { 3 },
+            data_type: JxlDataType::Uint8,
+            endianness: JxlEndianness::Native,
+            align: 0,
+        };
+        check(JxlEncoderAddImageFrame(
+            settings,
+            &format,
+            pixels.as_ptr().cast(),
+            pixels.len(),
+        ))?;
+        JxlEncoderCloseInput(encoder.0);
+    }
+
+    encoder.finish()
+}
+
+/// JPEG → JXL 无损转码，保存 JPEG 重建数据以便还原原始文件
+#[cfg(all(feature = "jxl-encode", not(target_arch = "wasm32")))]
+fn transcode_jpeg(data: &[u8]) -> Result<Vec<u8>, CompressorError> {
+    let encoder = Encoder::new()?;
+    unsafe {
+        check(JxlEncoderStoreJPEGMetadata(encoder.0, true.into()))?;
+        let settings = JxlEncoderFrameSettingsCreate(encoder.0, ptr::null());
+        check(JxlEncoderAddJPEGFrame(settings, data.as_ptr(), data.len()))?;
+        JxlEncoderCloseInput(encoder.0);
+    }
+
+    encoder.finish()
+}
+
+/// libjxl 是 C++ 库，只在启用 jxl-encode 的原生构建中链接
+#[cfg(not(all(feature = "jxl-encode", not(target_arch = "wasm32"))))]
+pub fn encode_jxl(_image: &image::DynamicImage, _quality: u8) -> Result<Vec<u8>, CompressorError> {
+    Err(CompressorError::JxlEncodeNotEnabled)
+}
+
+#[cfg(not(all(feature = "jxl-encode", not(target_arch = "wasm32"))))]
+fn transcode_jpeg(_data: &[u8]) -> Result<Vec<u8>, CompressorError> {
+    Err(CompressorError::JxlEncodeNotEnabled)
+}
+
+#[cfg(all(feature = "jxl-encode", not(target_arch = "wasm32")))]
+fn check(status: JxlEncoderStatus) -> Result<(), CompressorError> {
+    if status == JxlEncoderStatus::Success {
+        Ok(())
+    } else {
+        Err(CompressorError::EncodeError(
+            "JPEG XL encoding failed".to_string(),
+        ))
+    }
+}
+
+#[cfg(all(feature = "jxl-encode", not(target_arch = "wasm32")))]
+struct Encoder(*mut JxlEncoder);
+
+#[cfg(all(feature = "jxl-encode", not(target_arch = "wasm32")))]
+impl Encoder {
+    fn new() -> Result<Self, CompressorError> {
+        let encoder = unsafe { JxlEncoderCreate(ptr::null()) };
+        if encoder.is_null() {
+            return Err(CompressorError::EncodeError(
+                "Failed to create JPEG XL encoder".to_string(),
+            ));
+        }
+        Ok(Encoder(encoder))
+    }
+
+    /// 取出全部输出，缓冲区不足时按倍数扩容
+    fn finish(self) -> Result<Vec<u8>, CompressorError> {
+        let mut out = vec![0u8; 64 * 1024];
+        let mut written = 0;
+        loop {
+            let mut next = unsafe { out.as_mut_ptr().add(written) };
+            let mut avail = out.len() - written;
+            let status = unsafe { JxlEncoderProcessOutput(self.0, &mut next, &mut avail) };
+            written = out.len() - avail;
+            match status {
+                JxlEncoderStatus::Success => {
+                    out.truncate(written);
+                    return Ok(out);
+                }
+                JxlEncoderStatus::NeedMoreOutput => out.resize(out.len() * 2, 0),
+                _ => check(status)?,
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "jxl-encode", not(target_arch = "wasm32")))]
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        unsafe { JxlEncoderDestroy(self.0) };
+    }
+}
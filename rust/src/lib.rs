@@ -1,40 +1,104 @@
 use wasm_bindgen::prelude::*;
 
+mod alpha;
+mod animation;
+mod auto;
 mod avif;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod batch;
+mod decode;
 mod errors;
 mod exif;
+mod gif;
+mod icon;
 mod jpeg;
+mod memory;
+mod parallel;
 mod png;
+pub mod presets;
+pub mod progress;
+mod resize;
+mod stream;
+mod svg;
+mod tiff;
 mod utils;
+mod variants;
 #[cfg(target_arch = "wasm32")]
 mod c_compat;
 
 #[cfg(feature = "webp")]
 mod webp;
 
-use errors::CompressorError;
+#[cfg(feature = "heif")]
+mod heif;
+#[cfg(any(feature = "heif", all(feature = "avif-decode", not(target_arch = "wasm32"))))]
+mod icc;
+#[cfg(all(feature = "avif-decode", not(target_arch = "wasm32")))]
+mod avif_decode;
+#[cfg(feature = "jxl")]
+mod jxl;
+
+// 多线程构建需在 JS 中先调用 initThreadPool(navigator.hardwareConcurrency)
+#[cfg(all(feature = "threads", target_arch = "wasm32"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
+pub use errors::CompressorError;
+use resize::ResizeOptions;
 use utils::InputFormat;
 
+// 标准库分配器（wasm32 上为 dlmalloc）外加占用统计
 #[global_allocator]
-static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+static ALLOC: memory::TrackingAllocator = memory::TrackingAllocator;
 
 #[wasm_bindgen(start)]
 pub fn init() {
     console_error_panic_hook::set_once();
 }
 
-#[derive(Default, serde::Deserialize)]
-struct CompressOptions {
-    dithering: Option<bool>,
-    progressive: Option<bool>,
+/// compress_image 的选项，原生调用方可直接构造（均为可选，未设置时使用默认值）
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+pub struct CompressOptions {
+    pub dithering: Option<bool>,
+    pub progressive: Option<bool>,
     // 尺寸调整选项
-    resize_mode: Option<String>, // "none", "percentage", "maxWidth", "maxHeight", "fixed"
-    resize_value: Option<u32>,   // 百分比值或像素值
+    pub resize_mode: Option<String>, // "none", "percentage", "maxWidth", "maxHeight", "fixed"
+    pub resize_value: Option<u32>,   // 百分比值或像素值；contain/cover/fill/crop 时为宽度
+    pub resize_height: Option<u32>,  // contain/cover/fill/crop 的高度
+    pub crop_x: Option<u32>,         // crop 区域左上角
+    pub crop_y: Option<u32>,
+    pub gravity: Option<String>, // cover 的裁剪位置："center"（默认）或 "smart"
+    pub resize_filter: Option<String>, // "nearest", "triangle", "catmullRom", "gaussian", "lanczos3"（默认）
+    pub sharpen: Option<f32>,          // 缩小后 USM 锐化强度，0 为不锐化
+    pub linear_resize: Option<bool>,   // 在线性光空间缩放
+    pub allow_upscale: Option<bool>,   // 允许放大（默认不放大）
+    pub upscale_filter: Option<String>, // 放大算法：滤镜名称或 "edge"（边缘导向），默认沿用 resize_filter
     // EXIF处理选项
-    auto_rotate: Option<bool>,    // 自动旋转（根据EXIF方向）
-    strip_exif: Option<bool>,     // 清除EXIF元数据
+    pub auto_rotate: Option<bool>,    // 自动旋转（根据EXIF方向）
+    pub strip_exif: Option<bool>,     // 清除EXIF元数据
     // PNG 选项
-    png_truecolor: Option<bool>, // 保留真彩（不做调色板量化）
+    pub png_truecolor: Option<bool>, // 保留真彩（不做调色板量化）
+    // 完全透明像素的颜色处理："none"（默认）、"zero" 或 "bleed"；不透明图像同时去掉 alpha 通道
+    pub alpha_cleanup: Option<String>,
+    // 输出 JPEG 时透明区域的背景："#rrggbb"（默认白色）或 "checker"（预览用棋盘格）
+    pub background_color: Option<String>,
+    // 解码上限，超出时返回 LimitExceeded 而不是耗尽内存
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    pub max_pixels: Option<u64>,
+    pub max_alloc: Option<u64>, // 字节
+    // 多页 TIFF 的页码（从 0 开始）
+    pub page: Option<u32>,
+    // JPEG XL 选项
+    #[cfg(feature = "jxl")]
+    pub jxl_mode: Option<String>, // "lossy"（默认）或 "lossless-jpeg"（JPEG 无损转码）
+    // SVG 路径坐标保留的小数位数
+    pub svg_precision: Option<u8>,
+    // 响应式图片 <picture> 片段
+    pub base_name: Option<String>, // 变体文件名前缀，默认 "image"
+    pub alt: Option<String>,
+    pub sizes: Option<String>, // 默认 "100vw"
 }
 
 #[wasm_bindgen]
@@ -45,62 +109,467 @@ pub fn compress_image(
     options: JsValue,
 ) -> Result<Vec<u8>, JsValue> {
     let opts = parse_options(options)?;
+    compress(data, format, quality, &opts).map_err(map_err)
+}
+
+/// 压缩结果与实际输出的格式，format 为 "auto" 时是选中的格式
+#[wasm_bindgen]
+pub struct CompressedImage {
+    data: Vec<u8>,
+    format: String,
+}
+
+#[wasm_bindgen]
+impl CompressedImage {
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn format(&self) -> String {
+        self.format.clone()
+    }
+}
+
+/// 与 compress_image 相同，并在量化、编码等阶段调用 on_progress(stage, fraction)。
+/// cancel 为 SharedArrayBuffer 上的 Int32Array，主线程把第 0 项置为非 0 即可在下一个检查点中止，
+/// 此时返回 "Cancelled"。结果同时带有实际输出的格式
+#[wasm_bindgen]
+pub fn compress_image_with_progress(
+    data: &[u8],
+    format: &str,
+    quality: u8,
+    options: JsValue,
+    on_progress: Option<js_sys::Function>,
+    cancel: Option<js_sys::Int32Array>,
+) -> Result<CompressedImage, JsValue> {
+    let opts = parse_options(options)?;
+    let report = |event: &progress::Progress| {
+        if let Some(callback) = &on_progress {
+            // 回调抛出的异常不影响压缩
+            let _ = callback.call2(
+                &JsValue::NULL,
+                &JsValue::from_str(event.stage),
+                &JsValue::from_f64(f64::from(event.fraction)),
+            );
+        }
+    };
+    let is_cancelled = || {
+        cancel
+            .as_ref()
+            .is_some_and(|flag| js_sys::Atomics::load(flag, 0).unwrap_or(0) != 0)
+    };
+    let task = progress::Task::new(&report, &is_cancelled);
+    let (data, format) =
+        progress::run(&task, || compress_with_format(data, format, quality, &opts))
+            .map_err(map_err)?;
+    Ok(CompressedImage {
+        data,
+        format: format.to_string(),
+    })
+}
+
+/// 带进度回调与取消标记的 compress，回调可能在 rayon 工作线程上执行
+#[cfg(not(target_arch = "wasm32"))]
+pub fn compress_with_progress(
+    data: &[u8],
+    format: &str,
+    quality: u8,
+    opts: &CompressOptions,
+    on_progress: &(dyn Fn(&progress::Progress) + Sync),
+    cancel: &progress::CancelToken,
+) -> Result<Vec<u8>, CompressorError> {
+    let is_cancelled = || cancel.is_cancelled();
+    let task = progress::Task::new(on_progress, &is_cancelled);
+    progress::run(&task, || compress(data, format, quality, opts))
+}
+
+/// compress_image 的 Rust 入口，供原生调用方与批量处理使用
+pub fn compress(
+    data: &[u8],
+    format: &str,
+    quality: u8,
+    opts: &CompressOptions,
+) -> Result<Vec<u8>, CompressorError> {
+    compress_with_format(data, format, quality, opts).map(|(data, _)| data)
+}
+
+/// 与 compress 相同，同时返回实际输出的格式名；format 为 "auto" 时是选中的格式
+pub fn compress_with_format(
+    data: &[u8],
+    format: &str,
+    quality: u8,
+    opts: &CompressOptions,
+) -> Result<(Vec<u8>, &'static str), CompressorError> {
+    // 上限只作用于本次调用，批量处理中并行的任务互不影响
+    decode::with_limits(decode_limits(opts), || {
+        compress_with_limits(data, format, quality, opts)
+    })
+}
+
+fn compress_with_limits(
+    data: &[u8],
+    format: &str,
+    quality: u8,
+    opts: &CompressOptions,
+) -> Result<(Vec<u8>, &'static str), CompressorError> {
     let quality = quality.min(100);
 
-    let format = InputFormat::from_str(format).ok_or_else(|| {
-        JsValue::from_str(&CompressorError::UnsupportedFormat(format.to_string()).to_string())
-    })?;
+    let format = InputFormat::from_str(format)
+        .ok_or_else(|| CompressorError::UnsupportedFormat(format.to_string()))?;
 
-    let resize_mode = opts.resize_mode.as_deref().unwrap_or("none");
-    let resize_value = opts.resize_value.unwrap_or(100);
+    // 多页输入只压缩选中的一页
+    let page = decode::select_page(data, opts.page.unwrap_or(0))?;
+    let data: &[u8] = &page;
+
+    let resize = ResizeOptions {
+        mode: opts.resize_mode.as_deref().unwrap_or("none"),
+        value: opts.resize_value.unwrap_or(100),
+        height: opts.resize_height.unwrap_or(0),
+        x: opts.crop_x.unwrap_or(0),
+        y: opts.crop_y.unwrap_or(0),
+        gravity: opts.gravity.as_deref().unwrap_or("center"),
+        filter: resize::parse_filter(opts.resize_filter.as_deref().unwrap_or("lanczos3"))?,
+        sharpen: opts.sharpen.unwrap_or(0.0).max(0.0),
+        linear: opts.linear_resize.unwrap_or(false),
+        allow_upscale: opts.allow_upscale.unwrap_or(false),
+        upscaler: opts
+            .upscale_filter
+            .as_deref()
+            .map(resize::parse_upscaler)
+            .transpose()?,
+    };
     let auto_rotate = opts.auto_rotate.unwrap_or(true);
     let strip_exif = opts.strip_exif.unwrap_or(true);
     let png_truecolor = opts.png_truecolor.unwrap_or(false);
+    let alpha = alpha::parse_alpha_cleanup(opts.alpha_cleanup.as_deref().unwrap_or("none"))?;
+    let background = opts
+        .background_color
+        .as_deref()
+        .map(alpha::parse_background)
+        .transpose()?
+        .unwrap_or_default();
 
-    match format {
+    let output = match format {
         InputFormat::Png => png::compress_png(
             data,
             quality,
             opts.dithering.unwrap_or(true),
-            resize_mode,
-            resize_value,
+            &resize,
             auto_rotate,
             png_truecolor,
-        )
-        .map_err(map_err),
+            alpha,
+        ),
         InputFormat::Jpeg => jpeg::compress_jpeg(
             data,
             quality,
             opts.progressive.unwrap_or(true),
-            resize_mode,
-            resize_value,
+            &resize,
             auto_rotate,
             strip_exif,
-        )
-        .map_err(map_err),
+            background,
+        ),
         InputFormat::Webp => {
             #[cfg(feature = "webp")]
             {
-                webp::compress_webp(data, quality).map_err(map_err)
+                webp::compress_webp(data, quality, &resize, alpha)
             }
             #[cfg(not(feature = "webp"))]
             {
-                Err(map_err(CompressorError::WebpNotEnabled))
+                Err(CompressorError::WebpNotEnabled)
             }
         }
         InputFormat::Avif => {
             // AVIF 编码通过浏览器 Canvas API 实现
             // 这里返回一个占位符，实际编码在 worker 的 fallback 中处理
-            avif::compress_avif(data, quality).map_err(map_err)
+            avif::compress_avif(data, quality, alpha)
+        }
+        InputFormat::Gif => {
+            gif::compress_gif(data, quality, opts.dithering.unwrap_or(true), &resize)
         }
+        InputFormat::Jxl => {
+            #[cfg(feature = "jxl")]
+            {
+                jxl::compress_jxl(
+                    data,
+                    quality,
+                    opts.jxl_mode.as_deref().unwrap_or("lossy"),
+                    &resize,
+                    auto_rotate,
+                )
+            }
+            #[cfg(not(feature = "jxl"))]
+            {
+                Err(CompressorError::JxlNotEnabled)
+            }
+        }
+        InputFormat::Ico => icon::compress_ico(
+            data,
+            quality,
+            opts.dithering.unwrap_or(true),
+            auto_rotate,
+        ),
+        InputFormat::Svg => svg::compress_svg(data, opts.svg_precision.unwrap_or(3)),
+        InputFormat::Auto => {
+            return auto::compress_auto(
+                data,
+                quality,
+                opts.dithering.unwrap_or(true),
+                opts.progressive.unwrap_or(true),
+                &resize,
+                auto_rotate,
+                alpha,
+            )
+        }
+    };
+    output.map(|data| (data, format.name()))
+}
+
+/// 全部命名预设：[{ name, description, format, quality, options }]
+#[wasm_bindgen]
+pub fn list_presets() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&presets::all())
+        .map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+/// 按预设压缩，结果与原生的 presets::compress_preset 相同
+#[wasm_bindgen]
+pub fn compress_preset(data: &[u8], preset: &str) -> Result<Vec<u8>, JsValue> {
+    presets::compress_preset(data, preset).map_err(map_err)
+}
+
+/// 图标集中的单个文件
+#[wasm_bindgen]
+pub struct IconFile {
+    name: String,
+    data: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl IconFile {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+}
+
+/// 从一张源图生成 favicon.ico、apple-touch-icon.png 与 PWA 图标
+#[wasm_bindgen]
+pub fn generate_icons(
+    data: &[u8],
+    quality: u8,
+    options: JsValue,
+) -> Result<Vec<IconFile>, JsValue> {
+    let opts = parse_options(options)?;
+    let icons = decode::with_limits(decode_limits(&opts), || {
+        icon::generate_icon_set(
+            data,
+            quality.min(100),
+            opts.dithering.unwrap_or(true),
+            opts.auto_rotate.unwrap_or(true),
+        )
+    })
+    .map_err(map_err)?;
+
+    Ok(icons
+        .into_iter()
+        .map(|icon| IconFile {
+            name: icon.name.to_string(),
+            data: icon.data,
+        })
+        .collect())
+}
+
+/// 将 SVG 栅格化为 PNG 或 WebP，width 为 0 时使用 SVG 自身的尺寸
+#[wasm_bindgen]
+pub fn rasterize_svg(
+    data: &[u8],
+    format: &str,
+    width: u32,
+    quality: u8,
+    options: JsValue,
+) -> Result<Vec<u8>, JsValue> {
+    let opts = parse_options(options)?;
+    decode::with_limits(decode_limits(&opts), || {
+        svg::rasterize_svg(
+            data,
+            format,
+            width,
+            quality.min(100),
+            opts.dithering.unwrap_or(true),
+        )
+    })
+    .map_err(map_err)
+}
+
+/// 响应式图片的单个变体
+#[wasm_bindgen]
+pub struct ImageVariant {
+    name: String,
+    format: String,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl ImageVariant {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn format(&self) -> String {
+        self.format.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+}
+
+/// 全部变体以及对应的 <picture> 片段
+#[wasm_bindgen]
+pub struct VariantSet {
+    variants: Vec<ImageVariant>,
+    html: String,
+}
+
+#[wasm_bindgen]
+impl VariantSet {
+    /// 取出全部变体（只能调用一次）
+    #[wasm_bindgen(js_name = takeVariants)]
+    pub fn take_variants(&mut self) -> Vec<ImageVariant> {
+        std::mem::take(&mut self.variants)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn html(&self) -> String {
+        self.html.clone()
     }
 }
 
+/// 一张源图生成多宽度、多格式的变体，用于 srcset；widths/formats 为空时使用默认值
+#[wasm_bindgen]
+pub fn generate_variants(
+    data: &[u8],
+    widths: Vec<u32>,
+    formats: Vec<String>,
+    quality: u8,
+    options: JsValue,
+) -> Result<VariantSet, JsValue> {
+    let opts = parse_options(options)?;
+    let widths = if widths.is_empty() {
+        variants::DEFAULT_WIDTHS.to_vec()
+    } else {
+        widths
+    };
+    let formats = if formats.is_empty() {
+        variants::default_formats()
+    } else {
+        formats
+    };
+
+    let background = opts
+        .background_color
+        .as_deref()
+        .map(alpha::parse_background)
+        .transpose()
+        .map_err(map_err)?
+        .unwrap_or_default();
+    let generated = decode::with_limits(decode_limits(&opts), || {
+        variants::generate_variants(
+            data,
+            &widths,
+            &formats,
+            quality.min(100),
+            opts.dithering.unwrap_or(true),
+            opts.progressive.unwrap_or(true),
+            opts.auto_rotate.unwrap_or(true),
+            background,
+        )
+    })
+    .map_err(map_err)?;
+
+    let base_name = opts.base_name.as_deref().unwrap_or("image");
+    let html = variants::picture_html(
+        &generated,
+        base_name,
+        opts.alt.as_deref().unwrap_or(""),
+        opts.sizes.as_deref().unwrap_or("100vw"),
+    );
+
+    Ok(VariantSet {
+        variants: generated
+            .into_iter()
+            .map(|variant| ImageVariant {
+                name: variant.file_name(base_name),
+                format: variant.format.to_string(),
+                width: variant.width,
+                height: variant.height,
+                data: variant.data,
+            })
+            .collect(),
+        html,
+    })
+}
+
 #[wasm_bindgen]
 pub fn detect_format(data: &[u8]) -> String {
     utils::detect_format(data).unwrap_or("unknown").to_string()
 }
 
+/// 动画 GIF、APNG 与动画 WebP 返回 true
+#[wasm_bindgen]
+pub fn is_animated(data: &[u8]) -> bool {
+    utils::is_animated(data)
+}
+
+/// 多页 TIFF 返回页数，其他格式返回 1
+#[wasm_bindgen]
+pub fn get_page_count(data: &[u8]) -> u32 {
+    match utils::detect_format(data) {
+        Some("tiff") => tiff::page_count(data),
+        _ => 1,
+    }
+}
+
+/// 当前与峰值堆占用（字节），c_current / c_peak 为 C 代码经 malloc 分配的部分
+#[wasm_bindgen]
+pub fn memory_stats() -> memory::MemoryStats {
+    memory::stats()
+}
+
+/// 将峰值重置为当前占用，用于测量单个任务
+#[wasm_bindgen]
+pub fn reset_memory_peak() {
+    memory::reset_peak();
+}
+
+/// 释放批量处理间复用的像素缓冲区
+#[wasm_bindgen]
+pub fn release_buffers() {
+    memory::release_buffers();
+}
+
 #[wasm_bindgen]
 pub fn get_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
@@ -115,6 +584,16 @@ fn parse_options(options: JsValue) -> Result<CompressOptions, JsValue> {
         .map_err(|err| JsValue::from_str(&format!("Invalid options: {err}")))
 }
 
+fn decode_limits(opts: &CompressOptions) -> decode::DecodeLimits {
+    let default = decode::DecodeLimits::DEFAULT;
+    decode::DecodeLimits {
+        max_width: opts.max_width.unwrap_or(default.max_width),
+        max_height: opts.max_height.unwrap_or(default.max_height),
+        max_pixels: opts.max_pixels.unwrap_or(default.max_pixels),
+        max_alloc: opts.max_alloc.unwrap_or(default.max_alloc),
+    }
+}
+
 fn map_err(err: CompressorError) -> JsValue {
     JsValue::from_str(&err.to_string())
 }
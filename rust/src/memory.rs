@@ -0,0 +1,130 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use wasm_bindgen::prelude::*;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static C_CURRENT: AtomicUsize = AtomicUsize::new(0);
+static C_PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// 在标准库分配器（wasm32 上为 dlmalloc）外层统计当前与峰值堆占用
+pub struct TrackingAllocator;
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            grow(&CURRENT, &PEAK, layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            grow(&CURRENT, &PEAK, layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                grow(&CURRENT, &PEAK, new_size - layout.size());
+            } else {
+                CURRENT.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+            }
+        }
+        new_ptr
+    }
+}
+
+fn grow(current: &AtomicUsize, peak: &AtomicUsize, size: usize) {
+    let now = current.fetch_add(size, Ordering::Relaxed) + size;
+    peak.fetch_max(now, Ordering::Relaxed);
+}
+
+/// c_compat 的 malloc / free 单独记账（其字节同时计入总量）
+#[cfg(target_arch = "wasm32")]
+pub fn record_c_alloc(size: usize) {
+    grow(&C_CURRENT, &C_PEAK, size);
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn record_c_free(size: usize) {
+    C_CURRENT.fetch_sub(size, Ordering::Relaxed);
+}
+
+/// 每个线程空闲缓冲区的总容量上限（字节），超出时直接释放归还的缓冲区
+const POOL_BYTES: usize = 32 << 20;
+
+thread_local! {
+    static POOL: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// 取一个空缓冲区，容量沿用上一个任务，批量处理时不必反复向分配器申请整图大小的内存
+pub fn take_buffer() -> Vec<u8> {
+    POOL.with(|pool| pool.borrow_mut().pop()).unwrap_or_default()
+}
+
+/// 归还缓冲区供下一个任务复用
+pub fn recycle(mut buffer: Vec<u8>) {
+    buffer.clear();
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        let pooled: usize = pool.iter().map(Vec::capacity).sum();
+        if pooled + buffer.capacity() <= POOL_BYTES {
+            pool.push(buffer);
+        }
+    });
+}
+
+/// 释放池中缓冲区
+pub fn release_buffers() {
+    POOL.with(|pool| pool.borrow_mut().clear());
+}
+
+/// 堆占用快照（字节）
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryStats {
+    pub current: usize,
+    pub peak: usize,
+    pub c_current: usize,
+    pub c_peak: usize,
+    /// wasm 线性内存总大小；只增不减，原生构建为 0
+    pub heap_size: usize,
+}
+
+pub fn stats() -> MemoryStats {
+    MemoryStats {
+        current: CURRENT.load(Ordering::Relaxed),
+        peak: PEAK.load(Ordering::Relaxed),
+        c_current: C_CURRENT.load(Ordering::Relaxed),
+        c_peak: C_PEAK.load(Ordering::Relaxed),
+        heap_size: heap_size(),
+    }
+}
+
+/// 把峰值重置为当前占用，便于逐个任务测量
+pub fn reset_peak() {
+    PEAK.store(CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+    C_PEAK.store(C_CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn heap_size() -> usize {
+    core::arch::wasm32::memory_size(0) * 65536
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn heap_size() -> usize {
+    0
+}
@@ -0,0 +1,275 @@
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+/// 逐个求值并保持顺序。启用 threads feature 时在 rayon 线程池中并行执行
+pub fn map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync + Send,
+{
+    // 解码上限与当前任务的进度回调、取消检查都带到工作线程
+    #[cfg(all(feature = "threads", not(target_arch = "wasm32")))]
+    {
+        use rayon::prelude::*;
+        let task = crate::progress::shared();
+        let limits = crate::decode::limits();
+        items
+            .par_iter()
+            .map(|item| task.run(|| crate::decode::with_limits(limits, || f(item))))
+            .collect()
+    }
+    #[cfg(all(feature = "threads", target_arch = "wasm32"))]
+    {
+        use rayon::prelude::*;
+        let limits = crate::decode::limits();
+        crate::progress::relay(|relay| {
+            items
+                .par_iter()
+                .map(|item| relay.run(|| crate::decode::with_limits(limits, || f(item))))
+                .collect()
+        })
+    }
+    #[cfg(not(feature = "threads"))]
+    {
+        items.iter().map(f).collect()
+    }
+}
+
+/// 逐行处理 data，每行 row_len 个元素，回调参数为行号与该行
+#[cfg(feature = "threads")]
+fn for_each_row<T, F>(data: &mut [T], row_len: usize, f: F)
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync + Send,
+{
+    use rayon::prelude::*;
+    data.par_chunks_mut(row_len)
+        .enumerate()
+        .for_each(|(y, row)| f(y, row));
+}
+
+#[cfg(not(feature = "threads"))]
+pub fn resize_exact(
+    image: &DynamicImage,
+    width: u32,
+    height: u32,
+    filter: FilterType,
+) -> DynamicImage {
+    image.resize_exact(width, height, filter)
+}
+
+/// 与 image 库的 resize_exact 逐位一致，但两趟采样都按行分给线程池。
+/// 只处理 8 位图像，其他像素类型交给 image 库
+#[cfg(feature = "threads")]
+pub fn resize_exact(
+    image: &DynamicImage,
+    width: u32,
+    height: u32,
+    filter: FilterType,
+) -> DynamicImage {
+    let channels = match image {
+        DynamicImage::ImageLuma8(_) => 1,
+        DynamicImage::ImageLumaA8(_) => 2,
+        DynamicImage::ImageRgb8(_) => 3,
+        DynamicImage::ImageRgba8(_) => 4,
+        _ => return image.resize_exact(width, height, filter),
+    };
+    if (width, height) == (image.width(), image.height()) || width == 0 || height == 0 {
+        return image.resize_exact(width, height, filter);
+    }
+
+    let (src_width, src_height) = (image.width() as usize, image.height() as usize);
+    let src = image.as_bytes();
+    let (kernel, support) = filter_kernel(filter);
+
+    // 先纵向：每个输出行只依赖源图的若干行，结果保留为浮点
+    let rows: Vec<(usize, Vec<f32>)> = (0..height)
+        .map(|y| weights(y, src_height as u32, height, kernel, support))
+        .collect();
+    let mut vertical = vec![0f32; src_width * height as usize * channels];
+    for_each_row(&mut vertical, src_width * channels, |y, row| {
+        let (top, weights) = &rows[y];
+        for (x, value) in row.iter_mut().enumerate() {
+            let mut total = 0.0;
+            for (i, weight) in weights.iter().enumerate() {
+                total += f32::from(src[(top + i) * src_width * channels + x]) * weight;
+            }
+            *value = total;
+        }
+    });
+
+    // 再横向，取整并截断到 0..=255
+    let columns: Vec<(usize, Vec<f32>)> = (0..width)
+        .map(|x| weights(x, src_width as u32, width, kernel, support))
+        .collect();
+    let mut out = vec![0u8; width as usize * height as usize * channels];
+    for_each_row(&mut out, width as usize * channels, |y, row| {
+        let source = &vertical[y * src_width * channels..(y + 1) * src_width * channels];
+        for (x, (left, weights)) in columns.iter().enumerate() {
+            for channel in 0..channels {
+                let mut total = 0.0;
+                for (i, weight) in weights.iter().enumerate() {
+                    total += source[(left + i) * channels + channel] * weight;
+                }
+                row[x * channels + channel] = total.clamp(0.0, 255.0).round() as u8;
+            }
+        }
+    });
+
+    match channels {
+        1 => DynamicImage::ImageLuma8(image::GrayImage::from_raw(width, height, out).unwrap()),
+        2 => {
+            DynamicImage::ImageLumaA8(image::GrayAlphaImage::from_raw(width, height, out).unwrap())
+        }
+        3 => DynamicImage::ImageRgb8(image::RgbImage::from_raw(width, height, out).unwrap()),
+        _ => DynamicImage::ImageRgba8(image::RgbaImage::from_raw(width, height, out).unwrap()),
+    }
+}
+
+/// 输出坐标 out 对应的源像素起点与归一化权重，算法与 image 库相同
+#[cfg(feature = "threads")]
+fn weights(
+    out: u32,
+    src_len: u32,
+    dst_len: u32,
+    kernel: fn(f32) -> f32,
+    support: f32,
+) -> (usize, Vec<f32>) {
+    let ratio = src_len as f32 / dst_len as f32;
+    let sratio = if ratio < 1.0 { 1.0 } else { ratio };
+    let src_support = support * sratio;
+    let input = (out as f32 + 0.5) * ratio;
+
+    let left = ((input - src_support).floor() as i64).clamp(0, i64::from(src_len) - 1);
+    let right = ((input + src_support).ceil() as i64).clamp(left + 1, i64::from(src_len));
+    let input = input - 0.5;
+
+    let mut weights: Vec<f32> = (left..right)
+        .map(|i| kernel((i as f32 - input) / sratio))
+        .collect();
+    let sum: f32 = weights.iter().fold(0.0, |sum, weight| sum + weight);
+    weights.iter_mut().for_each(|weight| *weight /= sum);
+    (left as usize, weights)
+}
+
+#[cfg(feature = "threads")]
+fn filter_kernel(filter: FilterType) -> (fn(f32) -> f32, f32) {
+    fn sinc(t: f32) -> f32 {
+        let a = t * std::f32::consts::PI;
+        if t == 0.0 {
+            1.0
+        } else {
+            a.sin() / a
+        }
+    }
+    fn lanczos3(x: f32) -> f32 {
+        if x.abs() < 3.0 {
+            sinc(x) * sinc(x / 3.0)
+        } else {
+            0.0
+        }
+    }
+    // Mitchell–Netravali 三次样条，B = 0、C = 0.5
+    fn catmull_rom(x: f32) -> f32 {
+        let (b, c) = (0.0, 0.5);
+        let a = x.abs();
+        let k = if a < 1.0 {
+            (12.0 - 9.0 * b - 6.0 * c) * a.powi(3)
+                + (-18.0 + 12.0 * b + 6.0 * c) * a.powi(2)
+                + (6.0 - 2.0 * b)
+        } else if a < 2.0 {
+            (-b - 6.0 * c) * a.powi(3)
+                + (6.0 * b + 30.0 * c) * a.powi(2)
+                + (-12.0 * b - 48.0 * c) * a
+                + (8.0 * b + 24.0 * c)
+        } else {
+            0.0
+        };
+        k / 6.0
+    }
+    fn gaussian(x: f32) -> f32 {
+        let r: f32 = 0.5;
+        ((2.0 * std::f32::consts::PI).sqrt() * r).recip() * (-x.powi(2) / (2.0 * r.powi(2))).exp()
+    }
+    fn triangle(x: f32) -> f32 {
+        if x.abs() < 1.0 {
+            1.0 - x.abs()
+        } else {
+            0.0
+        }
+    }
+    fn nearest(_x: f32) -> f32 {
+        1.0
+    }
+
+    match filter {
+        FilterType::Nearest => (nearest, 0.0),
+        FilterType::Triangle => (triangle, 1.0),
+        FilterType::CatmullRom => (catmull_rom, 2.0),
+        FilterType::Gaussian => (gaussian, 3.0),
+        FilterType::Lanczos3 => (lanczos3, 3.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILTERS: [FilterType; 5] = [
+        FilterType::Nearest,
+        FilterType::Triangle,
+        FilterType::CatmullRom,
+        FilterType::Gaussian,
+        FilterType::Lanczos3,
+    ];
+    // 缩小、放大，以及只缩放一个方向
+    const SIZES: [(u32, u32); 3] = [(13, 9), (80, 61), (37, 11)];
+
+    fn sample(x: u32, y: u32, channel: u32) -> u8 {
+        ((x * 37 + y * 91 + channel * 53) ^ (x * y)) as u8
+    }
+
+    #[test]
+    fn resize_exact_matches_image_rgba8() {
+        let rgba = image::RgbaImage::from_fn(37, 23, |x, y| {
+            image::Rgba([
+                sample(x, y, 0),
+                sample(x, y, 1),
+                sample(x, y, 2),
+                sample(x, y, 3),
+            ])
+        });
+        let image = DynamicImage::ImageRgba8(rgba.clone());
+        for filter in FILTERS {
+            for (width, height) in SIZES {
+                let expected = image::imageops::resize(&rgba, width, height, filter);
+                let actual = resize_exact(&image, width, height, filter);
+                assert_eq!(
+                    actual.as_rgba8(),
+                    Some(&expected),
+                    "{filter:?} {width}x{height}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn resize_exact_matches_image_luma_alpha8() {
+        let luma_alpha = image::GrayAlphaImage::from_fn(37, 23, |x, y| {
+            image::LumaA([sample(x, y, 0), sample(x, y, 3)])
+        });
+        let image = DynamicImage::ImageLumaA8(luma_alpha.clone());
+        for filter in FILTERS {
+            for (width, height) in SIZES {
+                let expected = image::imageops::resize(&luma_alpha, width, height, filter);
+                let actual = resize_exact(&image, width, height, filter);
+                assert_eq!(
+                    actual.as_luma_alpha8(),
+                    Some(&expected),
+                    "{filter:?} {width}x{height}"
+                );
+            }
+        }
+    }
+}
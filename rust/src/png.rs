@@ -1,58 +1,72 @@
+use crate::alpha::AlphaCleanup;
+use crate::animation::{decode_animation, Animation};
 use crate::errors::CompressorError;
-
-fn apply_resize(image: image::DynamicImage, mode: &str, value: u32) -> image::DynamicImage {
-    match mode {
-        "percentage" => {
-            let scale = value as f32 / 100.0;
-            if scale >= 1.0 {
-                return image;
-            }
-            let new_width = (image.width() as f32 * scale).round() as u32;
-            let new_height = (image.height() as f32 * scale).round() as u32;
-            image.resize(new_width, new_height, image::imageops::FilterType::Lanczos3)
-        }
-        "maxWidth" => {
-            if image.width() <= value {
-                return image;
-            }
-            let scale = value as f32 / image.width() as f32;
-            let new_height = (image.height() as f32 * scale).round() as u32;
-            image.resize(value, new_height, image::imageops::FilterType::Lanczos3)
-        }
-        "maxHeight" => {
-            if image.height() <= value {
-                return image;
-            }
-            let scale = value as f32 / image.height() as f32;
-            let new_width = (image.width() as f32 * scale).round() as u32;
-            image.resize(new_width, value, image::imageops::FilterType::Lanczos3)
-        }
-        "fixed" => {
-            // 按比例缩放到指定宽度
-            let aspect_ratio = image.height() as f32 / image.width() as f32;
-            let new_height = (value as f32 * aspect_ratio).round() as u32;
-            image.resize(value, new_height, image::imageops::FilterType::Lanczos3)
-        }
-        _ => image,
-    }
-}
+use crate::resize::{apply_resize, ResizeOptions};
+use crate::utils::{detect_format, is_animated};
+use std::io::{Cursor, Write};
 
 pub fn compress_png(
     data: &[u8],
     quality: u8,
     dithering: bool,
-    resize_mode: &str,
-    resize_value: u32,
+    resize: &ResizeOptions,
     _auto_rotate: bool, // PNG 通常不包含 EXIF，保留参数以统一接口
     png_truecolor: bool,
+    alpha: AlphaCleanup,
 ) -> Result<Vec<u8>, CompressorError> {
-    let image = image::load_from_memory(data)?;
-    let image = apply_resize(image, resize_mode, resize_value);
+    // 不缩放时保留 APNG 的原始帧结构
+    if is_animated(data) && detect_format(data) == Some("png") && resize.is_none() {
+        return recompress_apng(data, quality, dithering, png_truecolor);
+    }
+
+    // 其他动图（GIF / 动态 WebP / 需要缩放的 APNG）按合成后的完整帧输出为 APNG
+    if is_animated(data) {
+        let animation = decode_animation(data)?.resize(resize)?;
+        return encode_apng(&animation, quality, dithering, png_truecolor);
+    }
+
+    // 真彩输出不需要量化，可以逐行转码；清理透明像素需要完整图像
+    if png_truecolor && resize.is_none() && alpha == AlphaCleanup::None {
+        if let Some(rows) = crate::stream::Rows::open(data, false)? {
+            return crate::stream::encode_png(rows);
+        }
+    }
+
+    let image = crate::decode::load_image(data)?;
+    let image = crate::alpha::clean(image, alpha);
+    let image = apply_resize(image, resize)?;
     // 注意：image 库在加载时已经自动应用了 EXIF 方向（如果存在）
-    let rgba = image.to_rgba8();
+    encode_png(&image, quality, dithering, png_truecolor)
+}
+
+pub fn encode_png(
+    image: &image::DynamicImage,
+    quality: u8,
+    dithering: bool,
+    png_truecolor: bool,
+) -> Result<Vec<u8>, CompressorError> {
+    // 没有 alpha 通道时真彩输出写 RGB，省去每像素一个字节
+    if png_truecolor && !image.color().has_alpha() {
+        let rgb = crate::utils::rgb8(image);
+        let (width, height) = rgb.dimensions();
+        crate::progress::report("encode", 0.0)?;
+        let mut out = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut out, width, height);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(rgb.as_raw())?;
+        }
+        crate::utils::recycle_image(rgb);
+        return Ok(out);
+    }
+
+    let rgba = crate::utils::rgba8(image);
     let (width, height) = rgba.dimensions();
 
     if png_truecolor {
+        crate::progress::report("encode", 0.0)?;
         let mut out = Vec::new();
         {
             let mut encoder = png::Encoder::new(&mut out, width, height);
@@ -61,71 +75,443 @@ pub fn compress_png(
             let mut writer = encoder.write_header()?;
             writer.write_image_data(rgba.as_raw())?;
         }
+        crate::utils::recycle_image(rgba);
         return Ok(out);
     }
 
-    let mut attr = imagequant::new();
-    let max_quality = quality;
-    let min_quality = max_quality.saturating_sub(20);
-    attr.set_quality(min_quality, max_quality)
-        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
-    attr.set_speed(3)
-        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
-
-    let pixels: Vec<imagequant::RGBA> = rgba
-        .as_raw()
-        .chunks_exact(4)
-        .map(|chunk| imagequant::RGBA {
-            r: chunk[0],
-            g: chunk[1],
-            b: chunk[2],
-            a: chunk[3],
-        })
-        .collect();
+    let attr = quantizer(quality)?;
 
     let mut img = attr
-        .new_image(pixels, width as usize, height as usize, 0.0)
+        .new_image_borrowed(
+            crate::utils::as_rgba_pixels(&rgba),
+            width as usize,
+            height as usize,
+            0.0,
+        )
         .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
 
-    let mut res = attr
-        .quantize(&mut img)
-        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+    let mut res = attr.quantize(&mut img)?;
 
     res.set_dithering_level(if dithering { 1.0 } else { 0.0 })
         .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
 
-    let (palette, pixels) = res
-        .remapped(&mut img)
-        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+    let mut pixels = crate::memory::take_buffer();
+    let palette = res.remap_into_vec(&mut img, &mut pixels)?;
+    drop(img);
+    crate::utils::recycle_image(rgba);
 
+    crate::progress::report("encode", 0.0)?;
     let mut out = Vec::new();
     {
         let mut encoder = png::Encoder::new(&mut out, width, height);
         encoder.set_color(png::ColorType::Indexed);
         encoder.set_depth(png::BitDepth::Eight);
+        set_palette(&mut encoder, &palette);
 
-        let mut palette_bytes = Vec::with_capacity(palette.len() * 3);
-        let mut trns = Vec::with_capacity(palette.len());
-        let mut has_alpha = false;
-
-        for color in &palette {
-            palette_bytes.push(color.r);
-            palette_bytes.push(color.g);
-            palette_bytes.push(color.b);
-            trns.push(color.a);
-            if color.a < 255 {
-                has_alpha = true;
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&pixels)?;
+    }
+    crate::memory::recycle(pixels);
+
+    Ok(out)
+}
+
+/// 编码 APNG，所有帧共用一个调色板
+pub fn encode_apng(
+    animation: &Animation,
+    quality: u8,
+    dithering: bool,
+    png_truecolor: bool,
+) -> Result<Vec<u8>, CompressorError> {
+    if !animation.is_animated() {
+        let image = image::DynamicImage::ImageRgba8(animation.frames[0].image.clone());
+        return encode_png(&image, quality, dithering, png_truecolor);
+    }
+
+    // 合成后的帧覆盖整个画布，直接替换即可
+    let controls: Vec<png::FrameControl> = animation
+        .frames
+        .iter()
+        .map(|frame| {
+            let (delay_num, delay_den) = frame_delay(frame.delay_ms);
+            png::FrameControl {
+                width: animation.width,
+                height: animation.height,
+                delay_num,
+                delay_den,
+                dispose_op: png::DisposeOp::None,
+                blend_op: png::BlendOp::Source,
+                ..png::FrameControl::default()
             }
-        }
+        })
+        .collect();
+    let images: Vec<&image::RgbaImage> = animation.frames.iter().map(|frame| &frame.image).collect();
+
+    write_apng(
+        (animation.width, animation.height),
+        animation.loop_count,
+        None,
+        &controls,
+        &images,
+        quality,
+        dithering,
+        png_truecolor,
+    )
+}
+
+/// 保留 APNG 的原始帧结构（位置、尺寸、时长、混合与处置方式）重新编码
+fn recompress_apng(
+    data: &[u8],
+    quality: u8,
+    dithering: bool,
+    png_truecolor: bool,
+) -> Result<Vec<u8>, CompressorError> {
+    let limits = png::Limits {
+        bytes: usize::try_from(crate::decode::limits().max_alloc).unwrap_or(usize::MAX),
+    };
+    let mut decoder = png::Decoder::new_with_limits(Cursor::new(data), limits);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let size = reader.info().size();
+    crate::decode::check_dimensions(size.0, size.1)?;
+    let animation_control = reader
+        .info()
+        .animation_control
+        .ok_or_else(|| CompressorError::DecodeError("Missing acTL chunk".to_string()))?;
 
-        encoder.set_palette(palette_bytes);
-        if has_alpha {
-            encoder.set_trns(trns);
+    let mut buf = vec![0; reader.output_buffer_size()];
+    // IDAT 前没有 fcTL 时，默认图像不属于动画，只给不支持 APNG 的查看器显示，原样保留
+    let default_image = if reader.info().frame_control.is_none() {
+        let output = reader.next_frame(&mut buf)?;
+        Some(to_rgba_image(&buf[..output.buffer_size()], &output)?)
+    } else {
+        None
+    };
+
+    let mut controls = Vec::with_capacity(animation_control.num_frames as usize);
+    let mut images = Vec::with_capacity(animation_control.num_frames as usize);
+    let mut total = 0;
+    for _ in 0..animation_control.num_frames {
+        total += buf.len() as u64;
+        crate::decode::check_alloc(total)?;
+        let output = reader.next_frame(&mut buf)?;
+        let control = reader
+            .info()
+            .frame_control
+            .ok_or_else(|| CompressorError::DecodeError("Missing fcTL chunk".to_string()))?;
+        controls.push(control);
+        images.push(to_rgba_image(&buf[..output.buffer_size()], &output)?);
+    }
+
+    let images: Vec<&image::RgbaImage> = images.iter().collect();
+    write_apng(
+        size,
+        animation_control.num_plays,
+        default_image.as_ref(),
+        &controls,
+        &images,
+        quality,
+        dithering,
+        png_truecolor,
+    )
+}
+
+/// default_image 为不属于动画的默认图像，写在第一个 fcTL 之前
+#[allow(clippy::too_many_arguments)]
+fn write_apng(
+    (width, height): (u32, u32),
+    num_plays: u32,
+    default_image: Option<&image::RgbaImage>,
+    controls: &[png::FrameControl],
+    images: &[&image::RgbaImage],
+    quality: u8,
+    dithering: bool,
+    png_truecolor: bool,
+) -> Result<Vec<u8>, CompressorError> {
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(images.len() as u32, num_plays)?;
+        if default_image.is_some() {
+            encoder.set_sep_def_img(true)?;
         }
 
+        // 默认图像与动画帧共用 PLTE，一起量化
+        let all: Vec<&image::RgbaImage> = default_image
+            .into_iter()
+            .chain(images.iter().copied())
+            .collect();
+        let frames: Vec<Vec<u8>> = if png_truecolor {
+            encoder.set_color(png::ColorType::Rgba);
+            all.iter().map(|image| image.as_raw().clone()).collect()
+        } else {
+            let (palette, frames) = quantize_shared(&all, quality, dithering)?;
+            encoder.set_color(png::ColorType::Indexed);
+            set_palette(&mut encoder, &palette);
+            frames
+        };
+
         let mut writer = encoder.write_header()?;
-        writer.write_image_data(&pixels)?;
+        let mut frames = frames.iter();
+        if default_image.is_some() {
+            // 默认图像覆盖整个画布，不写 fcTL
+            if let Some(pixels) = frames.next() {
+                writer.write_image_data(pixels)?;
+            }
+        }
+        for (i, (control, pixels)) in controls.iter().zip(frames).enumerate() {
+            crate::progress::report("encode", i as f32 / controls.len() as f32)?;
+            // 先归零偏移，否则新尺寸可能与上一帧的偏移冲突
+            writer.set_frame_position(0, 0)?;
+            writer.set_frame_dimension(control.width, control.height)?;
+            writer.set_frame_position(control.x_offset, control.y_offset)?;
+            writer.set_frame_delay(control.delay_num, control.delay_den)?;
+            writer.set_dispose_op(control.dispose_op)?;
+            writer.set_blend_op(control.blend_op)?;
+            writer.write_image_data(pixels)?;
+        }
     }
 
     Ok(out)
 }
+
+fn to_rgba_image(
+    buf: &[u8],
+    output: &png::OutputInfo,
+) -> Result<image::RgbaImage, CompressorError> {
+    let rgba: Vec<u8> = match output.color_type {
+        png::ColorType::Rgba => buf.to_vec(),
+        png::ColorType::Rgb => buf
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buf
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => {
+            return Err(CompressorError::DecodeError(
+                "Unexpanded indexed APNG frame".to_string(),
+            ))
+        }
+    };
+    image::RgbaImage::from_raw(output.width, output.height, rgba)
+        .ok_or_else(|| CompressorError::DecodeError("Invalid APNG frame size".to_string()))
+}
+
+/// 多帧共用调色板：各帧的颜色统计合并到同一个直方图中生成调色板，再逐帧映射。
+/// 映射时 imagequant 会用 k-means 微调调色板，因此先把颜色固定下来，保证各帧索引对应同一调色板
+fn quantize_shared(
+    images: &[&image::RgbaImage],
+    quality: u8,
+    dithering: bool,
+) -> Result<(Vec<imagequant::RGBA>, Vec<Vec<u8>>), CompressorError> {
+    fn borrowed<'a>(
+        attr: &imagequant::Attributes,
+        image: &'a image::RgbaImage,
+    ) -> Result<imagequant::Image<'a>, CompressorError> {
+        attr.new_image_borrowed(
+            crate::utils::as_rgba_pixels(image),
+            image.width() as usize,
+            image.height() as usize,
+            0.0,
+        )
+        .map_err(|err| CompressorError::EncodeError(err.to_string()))
+    }
+
+    let attr = quantizer(quality)?;
+    let mut histogram = imagequant::Histogram::new(&attr);
+    for image in images {
+        histogram.add_image(&attr, &mut borrowed(&attr, image)?)?;
+    }
+    let mut palette = histogram.quantize(&attr)?.palette_vec();
+
+    let mut res = imagequant::QuantizationResult::from_palette(&attr, &palette, 0.0)?;
+    res.set_dithering_level(if dithering { 1.0 } else { 0.0 })
+        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+
+    let mut frames = Vec::with_capacity(images.len());
+    for image in images {
+        let (remapped, indices) = res.remapped(&mut borrowed(&attr, image)?)?;
+        palette = remapped;
+        frames.push(indices);
+    }
+
+    Ok((palette, frames))
+}
+
+fn quantizer(quality: u8) -> Result<imagequant::Attributes, CompressorError> {
+    let mut attr = imagequant::new();
+    let max_quality = quality;
+    let min_quality = max_quality.saturating_sub(20);
+    attr.set_quality(min_quality, max_quality)
+        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+    attr.set_speed(3)
+        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+    attr.set_progress_callback(crate::progress::quantize_callback);
+    Ok(attr)
+}
+
+fn set_palette<W: Write>(encoder: &mut png::Encoder<'_, W>, palette: &[imagequant::RGBA]) {
+    let mut palette_bytes = Vec::with_capacity(palette.len() * 3);
+    let mut trns = Vec::with_capacity(palette.len());
+    let mut has_alpha = false;
+
+    for color in palette {
+        palette_bytes.push(color.r);
+        palette_bytes.push(color.g);
+        palette_bytes.push(color.b);
+        trns.push(color.a);
+        if color.a < 255 {
+            has_alpha = true;
+        }
+    }
+
+    encoder.set_palette(palette_bytes);
+    if has_alpha {
+        encoder.set_trns(trns);
+    }
+}
+
+/// APNG 帧延时为 u16 分数，超过 65535 毫秒时改用 1/100 秒为单位
+fn frame_delay(delay_ms: u32) -> (u16, u16) {
+    match u16::try_from(delay_ms) {
+        Ok(ms) => (ms, 1000),
+        Err(_) => (u16::try_from(delay_ms / 10).unwrap_or(u16::MAX), 100),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GREY: [u8; 4] = [128, 128, 128, 255];
+    const RED: [u8; 4] = [220, 20, 20, 255];
+    const GREEN: [u8; 4] = [20, 200, 40, 255];
+    const BLUE: [u8; 4] = [30, 40, 230, 255];
+    const YELLOW: [u8; 4] = [240, 220, 10, 255];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+    fn pixels(width: u32, height: u32, color: impl Fn(u32, u32) -> [u8; 4]) -> image::RgbaImage {
+        image::RgbaImage::from_fn(width, height, |x, y| image::Rgba(color(x, y)))
+    }
+
+    /// 默认图像为灰色且不属于动画；两帧颜色互不重叠，第二帧只覆盖 (2, 1) 处 4×3 的区域
+    fn apng_with_default_image() -> (Vec<u8>, [image::RgbaImage; 3]) {
+        let images = [
+            pixels(8, 6, |_, _| GREY),
+            pixels(8, 6, |x, _| if x % 2 == 0 { RED } else { GREEN }),
+            pixels(4, 3, |x, y| match (x + y) % 3 {
+                0 => BLUE,
+                1 => YELLOW,
+                _ => CLEAR,
+            }),
+        ];
+        let mut out = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut out, 8, 6);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(2, 3).unwrap();
+            encoder.set_sep_def_img(true).unwrap();
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(images[0].as_raw()).unwrap();
+            writer.set_frame_delay(1, 10).unwrap();
+            writer.write_image_data(images[1].as_raw()).unwrap();
+            writer.set_frame_dimension(4, 3).unwrap();
+            writer.set_frame_position(2, 1).unwrap();
+            writer.set_frame_delay(1, 5).unwrap();
+            writer.set_blend_op(png::BlendOp::Over).unwrap();
+            writer.write_image_data(images[2].as_raw()).unwrap();
+        }
+        (out, images)
+    }
+
+    fn assert_same_pixels(expected: &image::RgbaImage, actual: &image::RgbaImage, what: &str) {
+        assert_eq!(expected.dimensions(), actual.dimensions(), "{what}");
+        for (x, y, pixel) in expected.enumerate_pixels() {
+            let actual = actual.get_pixel(x, y);
+            if pixel[3] == 0 {
+                assert_eq!(actual[3], 0, "{what} at {x},{y}");
+            } else {
+                assert_eq!(pixel, actual, "{what} at {x},{y}");
+            }
+        }
+    }
+
+    #[test]
+    fn recompress_apng_keeps_default_image_and_frame_structure() {
+        let (data, images) = apng_with_default_image();
+        for truecolor in [false, true] {
+            let output = compress_png(
+                &data,
+                100,
+                false,
+                &ResizeOptions::new("none", 100),
+                false,
+                truecolor,
+                AlphaCleanup::None,
+            )
+            .unwrap();
+
+            let mut decoder = png::Decoder::new(Cursor::new(&output[..]));
+            decoder.set_transformations(png::Transformations::normalize_to_color8());
+            let mut reader = decoder.read_info().unwrap();
+            assert!(
+                reader.info().frame_control.is_none(),
+                "truecolor {truecolor}"
+            );
+            let animation = reader.info().animation_control.unwrap();
+            assert_eq!((animation.num_frames, animation.num_plays), (2, 3));
+
+            let mut buf = vec![0; reader.output_buffer_size()];
+            let mut layout = Vec::new();
+            for (i, expected) in images.iter().enumerate() {
+                let info = reader.next_frame(&mut buf).unwrap();
+                let actual = to_rgba_image(&buf[..info.buffer_size()], &info).unwrap();
+                assert_same_pixels(
+                    expected,
+                    &actual,
+                    &format!("image {i}, truecolor {truecolor}"),
+                );
+                if i > 0 {
+                    let control = reader.info().frame_control.unwrap();
+                    layout.push((
+                        control.x_offset,
+                        control.y_offset,
+                        control.width,
+                        control.height,
+                        control.delay_num,
+                        control.delay_den,
+                        control.blend_op,
+                    ));
+                }
+            }
+            assert_eq!(
+                layout,
+                [
+                    (0, 0, 8, 6, 1, 10, png::BlendOp::Source),
+                    (2, 1, 4, 3, 1, 5, png::BlendOp::Over),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn shared_palette_covers_every_frame() {
+        let images = [
+            pixels(6, 4, |x, _| if x < 3 { RED } else { GREEN }),
+            pixels(3, 5, |_, y| if y < 2 { BLUE } else { YELLOW }),
+        ];
+        let refs: Vec<&image::RgbaImage> = images.iter().collect();
+        let (palette, frames) = quantize_shared(&refs, 100, true).unwrap();
+        for (image, indices) in images.iter().zip(&frames) {
+            for (pixel, &index) in image.pixels().zip(indices) {
+                let color = palette[index as usize];
+                assert_eq!([color.r, color.g, color.b, color.a], pixel.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::errors::CompressorError;
+use crate::CompressOptions;
+
+/// 命名预设：输出格式、质量与完整的压缩选项。Web 界面、命令行与库调用共用同一份定义，
+/// 同一预设名得到相同的结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub format: &'static str,
+    pub quality: u8,
+    pub options: CompressOptions,
+}
+
+impl Preset {
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>, CompressorError> {
+        crate::compress(data, self.format, self.quality, &self.options)
+    }
+}
+
+pub const NAMES: [&str; 6] = [
+    "web-photo",
+    "thumbnail",
+    "lossless",
+    "max-compression",
+    "screenshot",
+    "print",
+];
+
+pub fn all() -> Vec<Preset> {
+    NAMES.iter().filter_map(|name| find(name)).collect()
+}
+
+pub fn find(name: &str) -> Option<Preset> {
+    let base = CompressOptions {
+        auto_rotate: Some(true),
+        strip_exif: Some(true),
+        ..CompressOptions::default()
+    };
+
+    let preset = match name {
+        "web-photo" => Preset {
+            name: "web-photo",
+            description: "Progressive JPEG for photos on web pages, at most 2048 px wide",
+            format: "jpeg",
+            quality: 80,
+            options: CompressOptions {
+                progressive: Some(true),
+                resize_mode: Some("maxWidth".to_string()),
+                resize_value: Some(2048),
+                ..base
+            },
+        },
+        "thumbnail" => Preset {
+            name: "thumbnail",
+            description: "320×320 crop around the most detailed region, smallest format",
+            format: "auto",
+            quality: 70,
+            options: CompressOptions {
+                resize_mode: Some("cover".to_string()),
+                resize_value: Some(320),
+                resize_height: Some(320),
+                gravity: Some("smart".to_string()),
+                sharpen: Some(0.5),
+                ..base
+            },
+        },
+        "lossless" => Preset {
+            name: "lossless",
+            description: "Truecolor PNG, pixels are kept exactly",
+            format: "png",
+            quality: 100,
+            options: CompressOptions {
+                png_truecolor: Some(true),
+                dithering: Some(false),
+                ..base
+            },
+        },
+        "max-compression" => Preset {
+            name: "max-compression",
+            description: "Smallest output at visibly reduced quality, at most 1920 px wide",
+            format: "auto",
+            quality: 50,
+            options: CompressOptions {
+                dithering: Some(true),
+                progressive: Some(true),
+                resize_mode: Some("maxWidth".to_string()),
+                resize_value: Some(1920),
+                ..base
+            },
+        },
+        "screenshot" => Preset {
+            name: "screenshot",
+            description: "Palette PNG without dithering, keeps text and flat colors sharp",
+            format: "png",
+            quality: 90,
+            options: CompressOptions {
+                dithering: Some(false),
+                ..base
+            },
+        },
+        "print" => Preset {
+            name: "print",
+            description: "High quality baseline JPEG at full resolution",
+            format: "jpeg",
+            quality: 95,
+            options: CompressOptions {
+                progressive: Some(false),
+                ..base
+            },
+        },
+        _ => return None,
+    };
+    Some(preset)
+}
+
+pub fn compress_preset(data: &[u8], name: &str) -> Result<Vec<u8>, CompressorError> {
+    find(name)
+        .ok_or_else(|| CompressorError::InvalidOption(format!("Unknown preset: {name}")))?
+        .compress(data)
+}
@@ -0,0 +1,213 @@
+use crate::errors::CompressorError;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// 进度事件：stage 为当前阶段（decode、resize、quantize、encode），fraction 为该阶段的完成比例
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub stage: &'static str,
+    pub fraction: f32,
+}
+
+/// 取消标记，可以在任意线程调用 cancel()
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// 原生构建中任务会传给 rayon 工作线程，回调必须是 Sync；wasm 中的 JS 函数只在调用线程上使用
+#[cfg(not(target_arch = "wasm32"))]
+type OnProgress<'a> = &'a (dyn Fn(&Progress) + Sync);
+#[cfg(not(target_arch = "wasm32"))]
+type IsCancelled<'a> = &'a (dyn Fn() -> bool + Sync);
+#[cfg(target_arch = "wasm32")]
+type OnProgress<'a> = &'a dyn Fn(&Progress);
+#[cfg(target_arch = "wasm32")]
+type IsCancelled<'a> = &'a dyn Fn() -> bool;
+
+/// 一次压缩的进度回调与取消检查
+pub struct Task<'a> {
+    on_progress: OnProgress<'a>,
+    is_cancelled: IsCancelled<'a>,
+}
+
+impl<'a> Task<'a> {
+    pub fn new(on_progress: OnProgress<'a>, is_cancelled: IsCancelled<'a>) -> Self {
+        Self {
+            on_progress,
+            is_cancelled,
+        }
+    }
+}
+
+thread_local! {
+    // 当前任务（擦除了生命周期），只在 run 的作用域内有效
+    static CURRENT: Cell<Option<*const ()>> = const { Cell::new(None) };
+}
+
+/// 在 f 执行期间把 task 设为当前任务，编码器通过 report / check 使用它
+pub fn run<R>(task: &Task, f: impl FnOnce() -> R) -> R {
+    install(Some(task as *const Task as *const ()), f)
+}
+
+fn install<R>(task: Option<*const ()>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<*const ()>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CURRENT.with(|current| current.set(self.0));
+        }
+    }
+
+    let _restore = Restore(CURRENT.with(|current| current.replace(task)));
+    f()
+}
+
+fn with_current<R>(f: impl FnOnce(&Task) -> R) -> Option<R> {
+    // SAFETY: 指针只在 run / install 的作用域内存在，期间任务一直有效
+    CURRENT.with(Cell::get).map(|task| f(unsafe { &*(task as *const Task) }))
+}
+
+/// 报告进度并检查取消，已取消时返回 Cancelled
+pub fn report(stage: &'static str, fraction: f32) -> Result<(), CompressorError> {
+    with_current(|task| {
+        (task.on_progress)(&Progress {
+            stage,
+            fraction: fraction.clamp(0.0, 1.0),
+        });
+    });
+    check()
+}
+
+/// 只检查取消
+pub fn check() -> Result<(), CompressorError> {
+    match with_current(|task| (task.is_cancelled)()) {
+        Some(true) => Err(CompressorError::Cancelled),
+        _ => Ok(()),
+    }
+}
+
+/// 逐行编码时每 64 行报告一次
+pub fn encode_row(y: u32, height: u32) -> Result<(), CompressorError> {
+    if !y.is_multiple_of(64) {
+        return Ok(());
+    }
+    report("encode", y as f32 / height.max(1) as f32)
+}
+
+/// 供 imagequant 使用的进度回调
+pub fn quantize_callback(percent: f32) -> imagequant::ControlFlow {
+    match report("quantize", percent / 100.0) {
+        Ok(()) => imagequant::ControlFlow::Continue,
+        Err(_) => imagequant::ControlFlow::Break,
+    }
+}
+
+/// 当前任务，用于传给其他线程
+#[cfg(all(feature = "threads", not(target_arch = "wasm32")))]
+#[derive(Clone, Copy)]
+pub struct Shared(Option<*const ()>);
+
+// SAFETY: 原生构建中任务的回调都是 Sync，且 parallel::map 返回前任务一直有效
+#[cfg(all(feature = "threads", not(target_arch = "wasm32")))]
+unsafe impl Send for Shared {}
+#[cfg(all(feature = "threads", not(target_arch = "wasm32")))]
+unsafe impl Sync for Shared {}
+
+#[cfg(all(feature = "threads", not(target_arch = "wasm32")))]
+pub fn shared() -> Shared {
+    Shared(CURRENT.with(Cell::get))
+}
+
+#[cfg(all(feature = "threads", not(target_arch = "wasm32")))]
+impl Shared {
+    pub fn run<R>(self, f: impl FnOnce() -> R) -> R {
+        install(self.0, f)
+    }
+}
+
+/// wasm 中 JS 回调与取消标记只能在调用线程上使用。
+/// 工作线程把进度放进队列、从原子标记读取取消状态，调用线程在等待期间转发进度并同步取消标记
+#[cfg(all(feature = "threads", target_arch = "wasm32"))]
+pub struct Relay {
+    events: std::sync::Mutex<Vec<Progress>>,
+    cancelled: AtomicBool,
+}
+
+#[cfg(all(feature = "threads", target_arch = "wasm32"))]
+thread_local! {
+    // 工作线程上正在使用的 Relay，嵌套的 parallel::map 直接沿用
+    static RELAY: Cell<Option<*const Relay>> = const { Cell::new(None) };
+}
+
+#[cfg(all(feature = "threads", target_arch = "wasm32"))]
+impl Relay {
+    /// 在工作线程上执行 f，期间的 report / check 经由 Relay 转给调用线程
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        let report = |event: &Progress| self.events.lock().unwrap().push(*event);
+        let is_cancelled = || self.cancelled.load(Ordering::Relaxed);
+        let task = Task::new(&report, &is_cancelled);
+        let previous = RELAY.with(|relay| relay.replace(Some(self as *const Relay)));
+        let result = run(&task, f);
+        RELAY.with(|relay| relay.set(previous));
+        result
+    }
+}
+
+/// 把当前任务带到 rayon 工作线程上执行 f。
+/// 已在某个 Relay 之内（嵌套调用）时直接沿用；否则 f 交给线程池，调用线程每 10ms 转发一次
+#[cfg(all(feature = "threads", target_arch = "wasm32"))]
+pub fn relay<R: Send>(f: impl FnOnce(&Relay) -> R + Send) -> R {
+    if let Some(active) = RELAY.with(Cell::get) {
+        // SAFETY: 外层 relay 返回前 Relay 一直有效
+        return f(unsafe { &*active });
+    }
+
+    struct Done<'a>(&'a AtomicBool);
+    impl Drop for Done<'_> {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::Release);
+        }
+    }
+
+    let relay = Relay {
+        events: std::sync::Mutex::new(Vec::new()),
+        cancelled: AtomicBool::new(false),
+    };
+    let done = AtomicBool::new(false);
+    let result = std::sync::Mutex::new(None);
+    rayon::in_place_scope(|scope| {
+        scope.spawn(|_| {
+            // f panic 时也要结束等待，panic 由 scope 继续传播
+            let _done = Done(&done);
+            *result.lock().unwrap() = Some(f(&relay));
+        });
+        loop {
+            let finished = done.load(Ordering::Acquire);
+            let events = std::mem::take(&mut *relay.events.lock().unwrap());
+            for event in events {
+                let _ = report(event.stage, event.fraction);
+            }
+            if check().is_err() {
+                relay.cancelled.store(true, Ordering::Relaxed);
+            }
+            if finished {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    });
+    result.into_inner().unwrap().unwrap()
+}
@@ -0,0 +1,555 @@
+use crate::errors::CompressorError;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageBuffer, Rgba};
+
+/// 缩小后锐化使用的高斯半径，只恢复缩放损失的边缘，不放大噪点
+const SHARPEN_SIGMA: f32 = 0.6;
+
+/// 智能裁剪时显著性图的最大边长
+const SALIENCY_SIZE: f32 = 256.0;
+
+/// 尺寸调整参数
+#[derive(Debug, Clone, Copy)]
+pub struct ResizeOptions<'a> {
+    /// "none", "percentage", "maxWidth", "maxHeight", "fixed",
+    /// 以及需要宽高的 "contain", "cover", "fill", "crop"
+    pub mode: &'a str,
+    /// 百分比值或像素值；contain / cover / fill / crop 时为目标宽度
+    pub value: u32,
+    /// contain / cover / fill / crop 的目标高度
+    pub height: u32,
+    /// crop 模式裁剪区域的左上角
+    pub x: u32,
+    pub y: u32,
+    /// cover 的裁剪位置："center" 或 "smart"（按显著性选择焦点）
+    pub gravity: &'a str,
+    pub filter: FilterType,
+    /// 缩小后 USM 锐化的强度，0 表示不锐化
+    pub sharpen: f32,
+    /// 在线性光空间而不是 gamma 编码的 sRGB 上插值
+    pub linear: bool,
+    /// 是否允许放大，所有模式统一遵守
+    pub allow_upscale: bool,
+    /// 放大时使用的缩放器，None 时沿用 filter
+    pub upscaler: Option<Upscaler>,
+}
+
+/// 放大算法
+#[derive(Debug, Clone, Copy)]
+pub enum Upscaler {
+    Filter(FilterType),
+    /// 边缘导向插值：逐次 2 倍放大，沿梯度较小的方向插值，斜线边缘不会出现锯齿
+    EdgeDirected,
+}
+
+impl<'a> ResizeOptions<'a> {
+    /// Lanczos3、不锐化、直接在 sRGB 上插值、不放大
+    pub fn new(mode: &'a str, value: u32) -> Self {
+        ResizeOptions {
+            mode,
+            value,
+            height: 0,
+            x: 0,
+            y: 0,
+            gravity: "center",
+            filter: FilterType::Lanczos3,
+            sharpen: 0.0,
+            linear: false,
+            allow_upscale: false,
+            upscaler: None,
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.mode == "none"
+    }
+}
+
+/// 解析缩放滤镜名称：像素画用 nearest，其余按清晰度从低到高为 triangle、gaussian、catmullRom、lanczos3
+pub fn parse_filter(name: &str) -> Result<FilterType, CompressorError> {
+    match name {
+        "nearest" => Ok(FilterType::Nearest),
+        "triangle" | "bilinear" => Ok(FilterType::Triangle),
+        "catmullRom" | "bicubic" => Ok(FilterType::CatmullRom),
+        "gaussian" => Ok(FilterType::Gaussian),
+        "lanczos3" => Ok(FilterType::Lanczos3),
+        _ => Err(CompressorError::InvalidOption(format!(
+            "unknown resize filter \"{name}\""
+        ))),
+    }
+}
+
+/// 解析放大算法："edge" 为边缘导向插值，其余同 parse_filter
+pub fn parse_upscaler(name: &str) -> Result<Upscaler, CompressorError> {
+    match name {
+        "edge" => Ok(Upscaler::EdgeDirected),
+        _ => parse_filter(name).map(Upscaler::Filter),
+    }
+}
+
+/// 目标尺寸同样受解码上限约束：允许放大时，过大的比例返回 LimitExceeded 而不是耗尽内存
+pub fn apply_resize(
+    image: DynamicImage,
+    options: &ResizeOptions,
+) -> Result<DynamicImage, CompressorError> {
+    // 框模式与 crop 必须给出宽和高，缺少时报错而不是静默跳过或裁出 1 像素的细条
+    let box_mode = matches!(options.mode, "contain" | "cover" | "fill" | "crop");
+    if box_mode && (options.value == 0 || options.height == 0) {
+        return Err(CompressorError::InvalidOption(format!(
+            "resize mode \"{}\" needs a non-zero width (resize_value) and resize_height",
+            options.mode
+        )));
+    }
+
+    let image = match options.mode {
+        "crop" => return crop(image, options),
+        "cover" => cover_crop(image, options),
+        _ => image,
+    };
+
+    let Some((width, height)) = target_size(image.width(), image.height(), options) else {
+        return Ok(image);
+    };
+    crate::decode::check_dimensions(width, height)?;
+
+    let area = u64::from(width) * u64::from(height);
+    let source_area = u64::from(image.width()) * u64::from(image.height());
+    let downscaled = area < source_area;
+
+    let filter = match options.upscaler {
+        Some(Upscaler::EdgeDirected) if area > source_area => {
+            return upscale_edge_directed(&image, width, height);
+        }
+        Some(Upscaler::Filter(filter)) if area > source_area => filter,
+        _ => options.filter,
+    };
+    // 线性光插值使用 4 × f32 的中间图
+    let bytes_per_pixel = if options.linear { 16 } else { 4 };
+    crate::decode::check_alloc(area * bytes_per_pixel)?;
+
+    let resized = if options.linear {
+        resize_linear(&image, width, height, filter)
+    } else {
+        crate::parallel::resize_exact(&image, width, height, filter)
+    };
+
+    if downscaled && options.sharpen > 0.0 {
+        Ok(unsharp_mask(resized, options.sharpen))
+    } else {
+        Ok(resized)
+    }
+}
+
+/// 计算目标尺寸；None 表示保持原图。
+/// 不允许放大时，超出原图的等比缩放保持原图，fill 的每个方向分别截到原图尺寸
+fn target_size(width: u32, height: u32, options: &ResizeOptions) -> Option<(u32, u32)> {
+    let value = options.value;
+    let scaled = |scale: f32| {
+        (
+            ((width as f32 * scale).round() as u32).max(1),
+            ((height as f32 * scale).round() as u32).max(1),
+        )
+    };
+
+    let (new_width, new_height) = match options.mode {
+        "percentage" => scaled(value as f32 / 100.0),
+        // maxWidth / maxHeight 与 fixed 一样等比缩放，只是默认不放大
+        "maxWidth" | "fixed" => scaled(value as f32 / width as f32),
+        "maxHeight" => scaled(value as f32 / height as f32),
+        "contain" => {
+            scaled((value as f32 / width as f32).min(options.height as f32 / height as f32))
+        }
+        // cover 已先裁剪到目标宽高比，这里与 fill 一样缩放到目标尺寸
+        "cover" | "fill" => (value, options.height),
+        _ => return None,
+    };
+
+    let (new_width, new_height) = if options.allow_upscale {
+        (new_width, new_height)
+    } else if options.mode == "fill" {
+        (new_width.min(width), new_height.min(height))
+    } else if new_width > width || new_height > height {
+        return None;
+    } else {
+        (new_width, new_height)
+    };
+
+    if (new_width, new_height) == (width, height) {
+        return None;
+    }
+    Some((new_width.max(1), new_height.max(1)))
+}
+
+/// 按矩形裁剪，超出图片的部分被截掉；矩形完全在图片之外时报错
+fn crop(image: DynamicImage, options: &ResizeOptions) -> Result<DynamicImage, CompressorError> {
+    if options.x >= image.width() || options.y >= image.height() {
+        return Err(CompressorError::InvalidOption(format!(
+            "crop rectangle at ({}, {}) is outside the {}x{} image",
+            options.x,
+            options.y,
+            image.width(),
+            image.height()
+        )));
+    }
+    let width = options.value.min(image.width() - options.x);
+    let height = options.height.min(image.height() - options.y);
+    Ok(image.crop_imm(options.x, options.y, width, height))
+}
+
+/// 从原图中裁出与目标宽高比一致的最大区域
+fn cover_crop(image: DynamicImage, options: &ResizeOptions) -> DynamicImage {
+    let (width, height) = (image.width(), image.height());
+    let target = options.value as f64 / options.height as f64;
+    let (crop_width, crop_height) = if width as f64 / height as f64 > target {
+        (
+            ((height as f64 * target).round() as u32).clamp(1, width),
+            height,
+        )
+    } else {
+        (
+            width,
+            ((width as f64 / target).round() as u32).clamp(1, height),
+        )
+    };
+    if (crop_width, crop_height) == (width, height) {
+        return image;
+    }
+
+    let (x, y) = if options.gravity == "smart" {
+        smart_offset(&image, crop_width, crop_height)
+    } else {
+        ((width - crop_width) / 2, (height - crop_height) / 2)
+    };
+    image.crop_imm(x, y, crop_width, crop_height)
+}
+
+/// 在缩小的显著性图上滑动裁剪窗口，取细节（亮度梯度）与饱和度总和最大的位置。
+/// 得分相近时略微偏向居中，避免纯色背景下裁到边缘
+fn smart_offset(image: &DynamicImage, crop_width: u32, crop_height: u32) -> (u32, u32) {
+    let (width, height) = (image.width(), image.height());
+    let scale = (SALIENCY_SIZE / width.max(height) as f32).min(1.0);
+    let thumb_width = ((width as f32 * scale).round() as usize).max(1);
+    let thumb_height = ((height as f32 * scale).round() as usize).max(1);
+    let thumb = image
+        .resize_exact(
+            thumb_width as u32,
+            thumb_height as u32,
+            FilterType::Triangle,
+        )
+        .to_rgb8();
+
+    let luma: Vec<f32> = thumb
+        .pixels()
+        .map(|p| 0.299 * f32::from(p[0]) + 0.587 * f32::from(p[1]) + 0.114 * f32::from(p[2]))
+        .collect();
+    let at = |x: usize, y: usize| luma[y * thumb_width + x];
+
+    // 积分图，便于 O(1) 求任意窗口内的得分
+    let stride = thumb_width + 1;
+    let mut integral = vec![0f64; stride * (thumb_height + 1)];
+    for y in 0..thumb_height {
+        let mut row = 0f64;
+        for x in 0..thumb_width {
+            let dx = at((x + 1).min(thumb_width - 1), y) - at(x.saturating_sub(1), y);
+            let dy = at(x, (y + 1).min(thumb_height - 1)) - at(x, y.saturating_sub(1));
+            let pixel = thumb.get_pixel(x as u32, y as u32);
+            let max = pixel.0.iter().max().copied().unwrap_or(0);
+            let min = pixel.0.iter().min().copied().unwrap_or(0);
+            row += f64::from(dx.abs() + dy.abs()) + f64::from(max - min) * 0.5;
+            integral[(y + 1) * stride + x + 1] = integral[y * stride + x + 1] + row;
+        }
+    }
+
+    let window_width = ((crop_width as f32 * scale).round() as usize).clamp(1, thumb_width);
+    let window_height = ((crop_height as f32 * scale).round() as usize).clamp(1, thumb_height);
+    let max_x = thumb_width - window_width;
+    let max_y = thumb_height - window_height;
+
+    let mut best = (0.0, max_x / 2, max_y / 2);
+    for y in 0..=max_y {
+        for x in 0..=max_x {
+            let sum = integral[(y + window_height) * stride + x + window_width]
+                - integral[y * stride + x + window_width]
+                - integral[(y + window_height) * stride + x]
+                + integral[y * stride + x];
+            let off_center = (x as f64 - max_x as f64 / 2.0).abs() / (max_x.max(1) as f64)
+                + (y as f64 - max_y as f64 / 2.0).abs() / (max_y.max(1) as f64);
+            let score = sum * (1.0 - 0.1 * off_center);
+            if score > best.0 {
+                best = (score, x, y);
+            }
+        }
+    }
+
+    let x = ((best.1 as f32 / scale).round() as u32).min(width - crop_width);
+    let y = ((best.2 as f32 / scale).round() as u32).min(height - crop_height);
+    (x, y)
+}
+
+/// 逐次 2 倍边缘导向放大到不小于目标尺寸，再用 Lanczos3 缩到精确尺寸
+fn upscale_edge_directed(
+    image: &DynamicImage,
+    width: u32,
+    height: u32,
+) -> Result<DynamicImage, CompressorError> {
+    // 最后一次加倍的中间图可能接近目标的 4 倍，先按它检查
+    let (mut doubled_width, mut doubled_height) =
+        (u64::from(image.width()), u64::from(image.height()));
+    while doubled_width < u64::from(width) || doubled_height < u64::from(height) {
+        doubled_width *= 2;
+        doubled_height *= 2;
+    }
+    crate::decode::check_alloc(doubled_width * doubled_height * 4)?;
+
+    let has_alpha = image.color().has_alpha();
+    let mut rgba = image.to_rgba8();
+    while rgba.width() < width || rgba.height() < height {
+        rgba = double_edge_directed(&rgba);
+    }
+
+    let out = image::imageops::resize(&rgba, width, height, FilterType::Lanczos3);
+    if has_alpha {
+        Ok(DynamicImage::ImageRgba8(out))
+    } else {
+        Ok(DynamicImage::ImageRgb8(
+            DynamicImage::ImageRgba8(out).to_rgb8(),
+        ))
+    }
+}
+
+/// 2 倍放大：偶数坐标为原像素；先沿亮度差较小的对角线插出格子中心，
+/// 再用原像素与中心点沿差值较小的方向插出边中点
+fn double_edge_directed(src: &image::RgbaImage) -> image::RgbaImage {
+    let (width, height) = (src.width() * 2, src.height() * 2);
+    let mut out = image::RgbaImage::new(width, height);
+    let luma =
+        |p: &Rgba<u8>| 0.299 * f32::from(p[0]) + 0.587 * f32::from(p[1]) + 0.114 * f32::from(p[2]);
+    let average = |pixels: &[Rgba<u8>]| {
+        let mut sum = [0u32; 4];
+        for pixel in pixels {
+            for (total, channel) in sum.iter_mut().zip(pixel.0) {
+                *total += u32::from(channel);
+            }
+        }
+        let count = pixels.len() as u32;
+        Rgba(sum.map(|total| ((total + count / 2) / count) as u8))
+    };
+    // 沿差值较小的一对插值，相等时四点平均
+    let interpolate = |a: Rgba<u8>, b: Rgba<u8>, c: Rgba<u8>, d: Rgba<u8>| {
+        let first = (luma(&a) - luma(&b)).abs();
+        let second = (luma(&c) - luma(&d)).abs();
+        if first < second {
+            average(&[a, b])
+        } else if second < first {
+            average(&[c, d])
+        } else {
+            average(&[a, b, c, d])
+        }
+    };
+
+    for (x, y, pixel) in src.enumerate_pixels() {
+        out.put_pixel(x * 2, y * 2, *pixel);
+    }
+
+    let source = |x: u32, y: u32| *src.get_pixel(x.min(src.width() - 1), y.min(src.height() - 1));
+    for y in 0..src.height() {
+        for x in 0..src.width() {
+            let center = interpolate(
+                source(x, y),
+                source(x + 1, y + 1),
+                source(x + 1, y),
+                source(x, y + 1),
+            );
+            out.put_pixel(x * 2 + 1, y * 2 + 1, center);
+        }
+    }
+
+    // 越界的邻点按边界镜像：奇偶性不变，取到的仍是已写入的原像素或格子中心，
+    // 不会读到尚未写入的边中点（夹到边界会在右侧和底部留下一像素的暗边）
+    let reflect = |value: i64, size: u32| {
+        let last = i64::from(size) - 1;
+        if value < 0 {
+            -value
+        } else if value > last {
+            2 * last - value
+        } else {
+            value
+        }
+        .clamp(0, last) as u32
+    };
+    let at = |out: &image::RgbaImage, x: i64, y: i64| {
+        *out.get_pixel(reflect(x, width), reflect(y, height))
+    };
+    for y in 0..i64::from(height) {
+        // 奇偶性相反的点是边中点：左右和上下各有一对已知点
+        for x in ((y + 1) % 2..i64::from(width)).step_by(2) {
+            let pixel = interpolate(
+                at(&out, x - 1, y),
+                at(&out, x + 1, y),
+                at(&out, x, y - 1),
+                at(&out, x, y + 1),
+            );
+            out.put_pixel(x as u32, y as u32, pixel);
+        }
+    }
+    out
+}
+
+/// 线性光空间插值：sRGB 解码为线性值并预乘 Alpha 后缩放，避免细节变暗和透明边缘发黑
+fn resize_linear(
+    image: &DynamicImage,
+    width: u32,
+    height: u32,
+    filter: FilterType,
+) -> DynamicImage {
+    let has_alpha = image.color().has_alpha();
+    let rgba = image.to_rgba8();
+
+    let to_linear: Vec<f32> = (0..=255u8)
+        .map(|value| srgb_to_linear(f32::from(value) / 255.0))
+        .collect();
+    let mut linear: ImageBuffer<Rgba<f32>, Vec<f32>> =
+        ImageBuffer::new(rgba.width(), rgba.height());
+    for (src, dst) in rgba.pixels().zip(linear.pixels_mut()) {
+        let alpha = f32::from(src[3]) / 255.0;
+        *dst = Rgba([
+            to_linear[src[0] as usize] * alpha,
+            to_linear[src[1] as usize] * alpha,
+            to_linear[src[2] as usize] * alpha,
+            alpha,
+        ]);
+    }
+
+    let resized = image::imageops::resize(&linear, width, height, filter);
+
+    let mut out = image::RgbaImage::new(width, height);
+    for (src, dst) in resized.pixels().zip(out.pixels_mut()) {
+        let alpha = src[3].clamp(0.0, 1.0);
+        let channel = |value: f32| {
+            let value = if alpha > 0.0 { value / alpha } else { 0.0 };
+            (linear_to_srgb(value.clamp(0.0, 1.0)) * 255.0).round() as u8
+        };
+        *dst = Rgba([
+            channel(src[0]),
+            channel(src[1]),
+            channel(src[2]),
+            (alpha * 255.0).round() as u8,
+        ]);
+    }
+
+    if has_alpha {
+        DynamicImage::ImageRgba8(out)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(out).to_rgb8())
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// USM 锐化：原图加上 amount 倍的（原图 - 高斯模糊），只作用于颜色通道
+fn unsharp_mask(image: DynamicImage, amount: f32) -> DynamicImage {
+    let has_alpha = image.color().has_alpha();
+    let mut rgba = image.to_rgba8();
+    let blurred = image::imageops::blur(&rgba, SHARPEN_SIGMA);
+
+    for (pixel, blur) in rgba.pixels_mut().zip(blurred.pixels()) {
+        for channel in 0..3 {
+            let value = f32::from(pixel[channel]);
+            let detail = value - f32::from(blur[channel]);
+            pixel[channel] = (value + amount * detail).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    if has_alpha {
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: u32, height: u32) -> image::RgbaImage {
+        image::RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([(x * 40) as u8, (y * 40) as u8, ((x + y) * 20) as u8, 255])
+        })
+    }
+
+    #[test]
+    fn edge_directed_keeps_opaque_input_opaque() {
+        let doubled = double_edge_directed(&gradient(5, 3));
+        assert_eq!(doubled.dimensions(), (10, 6));
+        assert!(doubled.pixels().all(|pixel| pixel[3] == 255));
+
+        let options = ResizeOptions {
+            allow_upscale: true,
+            upscaler: Some(Upscaler::EdgeDirected),
+            ..ResizeOptions::new("fixed", 23)
+        };
+        let resized = apply_resize(DynamicImage::ImageRgba8(gradient(5, 3)), &options).unwrap();
+        assert_eq!(resized.width(), 23);
+        assert!(resized.to_rgba8().pixels().all(|pixel| pixel[3] == 255));
+    }
+
+    #[test]
+    fn box_modes_need_width_and_height() {
+        for mode in ["contain", "cover", "fill", "crop"] {
+            for (value, height) in [(100, 0), (0, 100)] {
+                let options = ResizeOptions {
+                    height,
+                    ..ResizeOptions::new(mode, value)
+                };
+                let result = apply_resize(DynamicImage::ImageRgba8(gradient(8, 8)), &options);
+                assert!(matches!(result, Err(CompressorError::InvalidOption(_))));
+            }
+        }
+
+        let outside = ResizeOptions {
+            height: 4,
+            x: 8,
+            ..ResizeOptions::new("crop", 4)
+        };
+        let result = apply_resize(DynamicImage::ImageRgba8(gradient(8, 8)), &outside);
+        assert!(matches!(result, Err(CompressorError::InvalidOption(_))));
+
+        let clipped = ResizeOptions {
+            height: 4,
+            x: 6,
+            y: 2,
+            ..ResizeOptions::new("crop", 4)
+        };
+        let cropped = apply_resize(DynamicImage::ImageRgba8(gradient(8, 8)), &clipped).unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (2, 4));
+    }
+
+    #[test]
+    fn oversized_upscale_is_rejected() {
+        for (mode, value) in [("percentage", 100_000), ("fixed", 1_000_000)] {
+            for upscaler in [None, Some(Upscaler::EdgeDirected)] {
+                let options = ResizeOptions {
+                    allow_upscale: true,
+                    upscaler,
+                    ..ResizeOptions::new(mode, value)
+                };
+                let result = apply_resize(DynamicImage::ImageRgba8(gradient(64, 64)), &options);
+                assert!(matches!(result, Err(CompressorError::LimitExceeded(_))));
+            }
+        }
+    }
+}
@@ -0,0 +1,366 @@
+use crate::alpha::Background;
+use crate::decode::check_dimensions;
+use crate::errors::CompressorError;
+use crate::utils::detect_format;
+#[cfg(not(target_arch = "wasm32"))]
+use mozjpeg::Format;
+use std::io::{Cursor, Write};
+
+/// 逐行解码的输入，每次产出一行 RGBA8。
+/// 不需要缩放、量化、旋转等整图操作时使用，峰值内存只有几行像素而不是整帧
+pub enum Rows<'a> {
+    Png {
+        reader: png::Reader<Cursor<&'a [u8]>>,
+        channels: usize,
+        sixteen_bit: bool,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    Jpeg {
+        decompress: mozjpeg::Format<&'a [u8]>,
+        line: Vec<u8>,
+        channels: usize,
+    },
+}
+
+impl<'a> Rows<'a> {
+    /// 无法逐行处理的输入（隔行 PNG、CMYK JPEG、需要按 EXIF 旋转等）返回 None，由调用方完整解码
+    pub fn open(data: &'a [u8], auto_rotate: bool) -> Result<Option<Rows<'a>>, CompressorError> {
+        if auto_rotate
+            && crate::exif::get_exif_orientation(data).is_ok_and(|orientation| orientation != 1)
+        {
+            return Ok(None);
+        }
+        match detect_format(data) {
+            Some("png") => open_png(data),
+            #[cfg(not(target_arch = "wasm32"))]
+            Some("jpeg") => open_jpeg(data),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        match self {
+            Rows::Png { reader, .. } => reader.info().width,
+            #[cfg(not(target_arch = "wasm32"))]
+            Rows::Jpeg {
+                decompress:
+                    Format::RGB(decompress) | Format::Gray(decompress) | Format::CMYK(decompress),
+                ..
+            } => decompress.width() as u32,
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match self {
+            Rows::Png { reader, .. } => reader.info().height,
+            #[cfg(not(target_arch = "wasm32"))]
+            Rows::Jpeg {
+                decompress:
+                    Format::RGB(decompress) | Format::Gray(decompress) | Format::CMYK(decompress),
+                ..
+            } => decompress.height() as u32,
+        }
+    }
+
+    /// 读取下一行到 rgba（长度为宽度 × 4），转换规则与 image 库的 to_rgba8 一致
+    pub fn next_row(&mut self, rgba: &mut [u8]) -> Result<(), CompressorError> {
+        match self {
+            Rows::Png {
+                reader,
+                channels,
+                sixteen_bit,
+            } => {
+                let row = reader.next_row()?.ok_or_else(|| {
+                    CompressorError::DecodeError("Unexpected end of image".to_string())
+                })?;
+                let data = row.data();
+                let sample = |index: usize| {
+                    if *sixteen_bit {
+                        let value = u16::from_be_bytes([data[index * 2], data[index * 2 + 1]]);
+                        ((u32::from(value) + 128) / 257) as u8
+                    } else {
+                        data[index]
+                    }
+                };
+                for (x, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+                    let base = x * *channels;
+                    let (r, g, b, a) = match *channels {
+                        1 => (sample(base), sample(base), sample(base), 255),
+                        2 => (sample(base), sample(base), sample(base), sample(base + 1)),
+                        3 => (sample(base), sample(base + 1), sample(base + 2), 255),
+                        _ => (
+                            sample(base),
+                            sample(base + 1),
+                            sample(base + 2),
+                            sample(base + 3),
+                        ),
+                    };
+                    pixel.copy_from_slice(&[r, g, b, a]);
+                }
+                Ok(())
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Rows::Jpeg {
+                decompress:
+                    Format::RGB(decompress) | Format::Gray(decompress) | Format::CMYK(decompress),
+                line,
+                channels,
+            } => {
+                // libjpeg 的错误以 panic 形式抛出
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    decompress.read_scanlines_into::<u8>(line).map(|_| ())
+                }))
+                .map_err(|_| CompressorError::DecodeError("Corrupt JPEG data".to_string()))?
+                .map_err(|err| CompressorError::DecodeError(err.to_string()))?;
+                for (pixel, color) in rgba.chunks_exact_mut(4).zip(line.chunks_exact(*channels)) {
+                    let (r, g, b) = match color {
+                        [r, g, b] => (*r, *g, *b),
+                        _ => (color[0], color[0], color[0]),
+                    };
+                    pixel.copy_from_slice(&[r, g, b, 255]);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// 所有行读完后校验输入结尾
+    pub fn finish(self) -> Result<(), CompressorError> {
+        match self {
+            Rows::Png { .. } => Ok(()),
+            #[cfg(not(target_arch = "wasm32"))]
+            Rows::Jpeg {
+                decompress:
+                    Format::RGB(decompress) | Format::Gray(decompress) | Format::CMYK(decompress),
+                ..
+            } => std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| decompress.finish()))
+                .map_err(|_| CompressorError::DecodeError("Corrupt JPEG data".to_string()))?
+                .map_err(|err| CompressorError::DecodeError(err.to_string())),
+        }
+    }
+}
+
+fn open_png(data: &[u8]) -> Result<Option<Rows<'_>>, CompressorError> {
+    let mut decoder = png::Decoder::new(Cursor::new(data));
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let Ok(reader) = decoder.read_info() else {
+        return Ok(None);
+    };
+    // 隔行扫描的行按 Adam7 分趟输出，无法顺序写出
+    if reader.info().interlaced {
+        return Ok(None);
+    }
+    check_dimensions(reader.info().width, reader.info().height)?;
+
+    let (color, depth) = reader.output_color_type();
+    Ok(Some(Rows::Png {
+        reader,
+        channels: color.samples(),
+        sixteen_bit: depth == png::BitDepth::Sixteen,
+    }))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn open_jpeg(data: &[u8]) -> Result<Option<Rows<'_>>, CompressorError> {
+    let Ok(Ok(decompress)) = std::panic::catch_unwind(|| {
+        mozjpeg::Decompress::builder()
+            .with_err(strict_error_mgr())
+            .from_mem(data)
+    }) else {
+        return Ok(None);
+    };
+    if !matches!(
+        decompress.color_space(),
+        mozjpeg::ColorSpace::JCS_YCbCr
+            | mozjpeg::ColorSpace::JCS_GRAYSCALE
+            | mozjpeg::ColorSpace::JCS_RGB
+    ) {
+        return Ok(None);
+    }
+    let (width, height) = decompress.size();
+    check_dimensions(width as u32, height as u32)?;
+
+    // YCbCr 输出 RGB，灰度保持单通道
+    let Ok(Ok(decompress)) =
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| decompress.image()))
+    else {
+        return Ok(None);
+    };
+    let channels = match decompress {
+        Format::RGB(_) => 3,
+        Format::Gray(_) => 1,
+        Format::CMYK(_) => return Ok(None),
+    };
+    Ok(Some(Rows::Jpeg {
+        decompress,
+        line: vec![0; width * channels],
+        channels,
+    }))
+}
+
+/// mozjpeg 默认忽略警告，截断的文件会以灰色补齐。这里把警告也当作错误，与完整解码路径一致
+#[cfg(not(target_arch = "wasm32"))]
+fn strict_error_mgr() -> mozjpeg_sys::jpeg_error_mgr {
+    unsafe extern "C-unwind" fn error_exit(_cinfo: &mut mozjpeg_sys::jpeg_common_struct) {
+        std::panic::resume_unwind(Box::new("libjpeg fatal error"));
+    }
+
+    unsafe extern "C-unwind" fn emit_message(
+        cinfo: &mut mozjpeg_sys::jpeg_common_struct,
+        level: std::os::raw::c_int,
+    ) {
+        if level < 0 {
+            error_exit(cinfo);
+        }
+    }
+
+    unsafe {
+        let mut err = std::mem::zeroed();
+        mozjpeg_sys::jpeg_std_error(&mut err);
+        err.error_exit = Some(error_exit);
+        err.emit_message = Some(emit_message);
+        err
+    }
+}
+
+/// 逐行写出真彩 PNG
+pub fn encode_png(mut rows: Rows) -> Result<Vec<u8>, CompressorError> {
+    let (width, height) = (rows.width(), rows.height());
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        let mut stream = writer.stream_writer()?;
+
+        let mut row = vec![0; width as usize * 4];
+        for y in 0..height {
+            crate::progress::encode_row(y, height)?;
+            rows.next_row(&mut row)?;
+            stream
+                .write_all(&row)
+                .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+        }
+        stream.finish()?;
+    }
+    rows.finish()?;
+    Ok(out)
+}
+
+/// 逐行写出基线 JPEG，透明像素合成到 background 上（与 alpha::flatten 一致）。
+/// 渐进式模式会缓存整幅图像的系数，调用方需要渐进式输出时不走这里
+#[cfg(not(target_arch = "wasm32"))]
+pub fn encode_jpeg(
+    mut rows: Rows,
+    quality: u8,
+    background: Background,
+) -> Result<Vec<u8>, CompressorError> {
+    let (width, height) = (rows.width() as usize, rows.height());
+
+    let mut comp = mozjpeg::Compress::new(mozjpeg::ColorSpace::JCS_RGB);
+    comp.set_size(width, height as usize);
+    comp.set_quality(quality as f32);
+
+    let mut comp = comp
+        .start_compress(Vec::new())
+        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+
+    let mut rgba = vec![0; width * 4];
+    let mut rgb = vec![0; width * 3];
+    for y in 0..height {
+        crate::progress::encode_row(y, height)?;
+        rows.next_row(&mut rgba)?;
+        for (x, (rgb, rgba)) in rgb.chunks_exact_mut(3).zip(rgba.chunks_exact(4)).enumerate() {
+            rgb.copy_from_slice(&background.blend(rgba, x as u32, y));
+        }
+        comp.write_scanlines(&rgb)
+            .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+    }
+
+    let jpeg_data = comp
+        .finish()
+        .map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+    rows.finish()?;
+    Ok(jpeg_data)
+}
+
+/// jpeg-encoder 的交错模式按 8/16 行一条带依次取行，只需缓存当前一行
+#[cfg(target_arch = "wasm32")]
+struct RowBuffer<'a> {
+    rows: std::cell::RefCell<Rows<'a>>,
+    row: std::cell::RefCell<Vec<u8>>,
+    next: std::cell::Cell<u32>,
+    error: std::cell::RefCell<Option<CompressorError>>,
+    background: Background,
+    width: u16,
+    height: u16,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl jpeg_encoder::ImageBuffer for &RowBuffer<'_> {
+    fn get_jpeg_color_type(&self) -> jpeg_encoder::JpegColorType {
+        jpeg_encoder::JpegColorType::Ycbcr
+    }
+
+    fn width(&self) -> u16 {
+        self.width
+    }
+
+    fn height(&self) -> u16 {
+        self.height
+    }
+
+    fn fill_buffers(&self, y: u16, buffers: &mut [Vec<u8>; 4]) {
+        let mut row = self.row.borrow_mut();
+        // 最后一条带不足时编码器会重复请求最后一行
+        while self.next.get() <= u32::from(y) && self.error.borrow().is_none() {
+            let next = crate::progress::encode_row(self.next.get(), u32::from(self.height))
+                .and_then(|()| self.rows.borrow_mut().next_row(&mut row));
+            if let Err(err) = next {
+                *self.error.borrow_mut() = Some(err);
+            }
+            self.next.set(self.next.get() + 1);
+        }
+        // 缓存的是第 next - 1 行
+        let row_index = self.next.get().saturating_sub(1);
+        for (x, pixel) in row.chunks_exact(4).enumerate() {
+            let [r, g, b] = self.background.blend(pixel, x as u32, row_index);
+            let (y, cb, cr) = jpeg_encoder::rgb_to_ycbcr(r, g, b);
+            buffers[0].push(y);
+            buffers[1].push(cb);
+            buffers[2].push(cr);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn encode_jpeg(
+    rows: Rows,
+    quality: u8,
+    background: Background,
+) -> Result<Vec<u8>, CompressorError> {
+    let too_large = || CompressorError::EncodeError("Image too large for JPEG".to_string());
+    let width = u16::try_from(rows.width()).map_err(|_| too_large())?;
+    let height = u16::try_from(rows.height()).map_err(|_| too_large())?;
+    let buffer = RowBuffer {
+        rows: std::cell::RefCell::new(rows),
+        row: std::cell::RefCell::new(vec![0; usize::from(width) * 4]),
+        next: std::cell::Cell::new(0),
+        error: std::cell::RefCell::new(None),
+        background,
+        width,
+        height,
+    };
+
+    let mut out = Vec::new();
+    let encoder = jpeg_encoder::Encoder::new(&mut out, quality);
+    let result = encoder.encode_image(&buffer);
+    if let Some(err) = buffer.error.take() {
+        return Err(err);
+    }
+    result.map_err(|err| CompressorError::EncodeError(err.to_string()))?;
+
+    buffer.rows.into_inner().finish()?;
+    Ok(out)
+}
@@ -0,0 +1,651 @@
+use crate::errors::CompressorError;
+use std::collections::HashSet;
+
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// 编辑器写入的私有命名空间，其中的元素、属性和声明全部删除
+const EDITOR_NAMESPACES: [&str; 14] = [
+    "http://www.inkscape.org/namespaces/inkscape",
+    "http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd",
+    "http://inkscape.sourceforge.net/DTD/sodipodi-0.dtd",
+    "http://ns.adobe.com/AdobeIllustrator/10.0/",
+    "http://ns.adobe.com/AdobeSVGViewerExtensions/3.0/",
+    "http://ns.adobe.com/Extensibility/1.0/",
+    "http://ns.adobe.com/Flows/1.0/",
+    "http://ns.adobe.com/Graphs/1.0/",
+    "http://ns.adobe.com/ImageReplacement/1.0/",
+    "http://ns.adobe.com/SaveForWeb/1.0/",
+    "http://ns.adobe.com/Variables/1.0/",
+    "http://www.bohemiancoding.com/sketch/ns",
+    "http://www.serif.com/",
+    "http://www.vector.evaxdesign.sk",
+];
+
+/// 空白文本有意义的元素
+const TEXT_ELEMENTS: [&str; 5] = ["text", "tspan", "textPath", "style", "script"];
+
+/// 合并单子元素分组时不下移的属性：会改变选择器匹配或分组渲染结果
+const GROUP_ONLY_ATTRIBUTES: [&str; 6] = ["id", "class", "style", "filter", "mask", "clip-path"];
+
+/// 压缩 SVG：删除元数据、注释和编辑器命名空间，合并多余分组，按精度舍入路径坐标
+pub fn compress_svg(data: &[u8], precision: u8) -> Result<Vec<u8>, CompressorError> {
+    let text = std::str::from_utf8(data)
+        .map_err(|err| CompressorError::DecodeError(format!("SVG is not UTF-8: {err}")))?;
+    let text = text.trim_start_matches('\u{feff}');
+
+    let document = roxmltree::Document::parse_with_options(
+        text,
+        roxmltree::ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        },
+    )
+    .map_err(|err| CompressorError::DecodeError(err.to_string()))?;
+
+    let root = document.root_element();
+    if root.tag_name().name() != "svg" {
+        return Err(CompressorError::DecodeError(
+            "Not an SVG document".to_string(),
+        ));
+    }
+
+    let mut svg = convert(root, &[], precision.min(8) as usize)
+        .ok_or_else(|| CompressorError::DecodeError("Not an SVG document".to_string()))?;
+    collapse_groups(&mut svg);
+
+    let mut used = HashSet::new();
+    used_prefixes(&svg, &mut used);
+    remove_unused_namespaces(&mut svg, &used);
+
+    let mut out = String::with_capacity(text.len());
+    write_element(&svg, &mut out);
+    Ok(out.into_bytes())
+}
+
+/// 将 SVG 栅格化后编码为 PNG 或 WebP，width 为 0 时使用 SVG 自身的尺寸
+pub fn rasterize_svg(
+    data: &[u8],
+    format: &str,
+    width: u32,
+    quality: u8,
+    dithering: bool,
+) -> Result<Vec<u8>, CompressorError> {
+    let image = crate::decode::load_svg(data, width)?;
+    match format {
+        "png" => crate::png::encode_png(&image, quality, dithering, false),
+        "webp" => {
+            #[cfg(feature = "webp")]
+            {
+                crate::webp::encode_webp(&image, quality)
+            }
+            #[cfg(not(feature = "webp"))]
+            {
+                Err(CompressorError::WebpNotEnabled)
+            }
+        }
+        _ => Err(CompressorError::UnsupportedFormat(format.to_string())),
+    }
+}
+
+/// 系统字体只扫描一次。wasm 中没有系统字体，数据库为空
+#[cfg(feature = "svg-raster")]
+fn fonts() -> std::sync::Arc<resvg::usvg::fontdb::Database> {
+    use resvg::usvg::fontdb::{Database, Family, Query};
+
+    static FONTS: std::sync::OnceLock<std::sync::Arc<Database>> = std::sync::OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut database = Database::new();
+            #[cfg(not(target_arch = "wasm32"))]
+            database.load_system_fonts();
+            // usvg 找不到字体时最终回退到 serif，而 fontdb 默认把它映射到 Times New Roman，
+            // Linux 上通常没有，这时改用任意一个已安装的字体族
+            let serif = Query {
+                families: &[Family::Serif],
+                ..Default::default()
+            };
+            if database.query(&serif).is_none() {
+                let installed = database
+                    .faces()
+                    .find_map(|face| face.families.first())
+                    .map(|(family, _)| family.clone());
+                if let Some(family) = installed {
+                    database.set_serif_family(family);
+                }
+            }
+            std::sync::Arc::new(database)
+        })
+        .clone()
+}
+
+/// 用 resvg 渲染，输出去预乘的 RGBA。
+/// 文字用系统字体排版；没有可用字体时（wasm）含 text 元素的 SVG 报错，而不是静默丢掉文字
+#[cfg(feature = "svg-raster")]
+pub fn rasterize(data: &[u8], width: u32) -> Result<image::DynamicImage, CompressorError> {
+    use resvg::{tiny_skia, usvg};
+
+    let fontdb = fonts();
+    if fontdb.is_empty() && has_text(data) {
+        return Err(CompressorError::DecodeError(
+            "SVG text cannot be rasterized: no fonts are available".to_string(),
+        ));
+    }
+    let options = usvg::Options {
+        fontdb,
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_data(data, &options)
+        .map_err(|err| CompressorError::DecodeError(err.to_string()))?;
+
+    let size = tree.size();
+    let scale = if width == 0 {
+        1.0
+    } else {
+        width as f32 / size.width()
+    };
+    let target_width = (size.width() * scale).round().max(1.0) as u32;
+    let target_height = (size.height() * scale).round().max(1.0) as u32;
+
+    crate::decode::check_dimensions(target_width, target_height)?;
+    let mut pixmap = tiny_skia::Pixmap::new(target_width, target_height)
+        .ok_or_else(|| CompressorError::DecodeError("Invalid SVG size".to_string()))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    let rgba = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    let buffer = image::RgbaImage::from_raw(target_width, target_height, rgba)
+        .ok_or_else(|| CompressorError::DecodeError("Invalid SVG size".to_string()))?;
+    Ok(image::DynamicImage::ImageRgba8(buffer))
+}
+
+/// 文档中是否有 SVG text 元素；无法解析时交给 usvg 报错
+#[cfg(feature = "svg-raster")]
+fn has_text(data: &[u8]) -> bool {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return false;
+    };
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let Ok(document) =
+        roxmltree::Document::parse_with_options(text.trim_start_matches('\u{feff}'), options)
+    else {
+        return false;
+    };
+    document
+        .descendants()
+        .any(|node| node.tag_name().namespace() == Some(SVG_NS) && node.tag_name().name() == "text")
+}
+
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+fn is_editor_namespace(namespace: Option<&str>) -> bool {
+    namespace.is_some_and(|uri| EDITOR_NAMESPACES.contains(&uri))
+}
+
+fn qualified_name(node: roxmltree::Node, namespace: Option<&str>, local: &str) -> String {
+    let prefix = match namespace {
+        Some(XML_NS) => Some("xml"),
+        Some(uri) => node.lookup_prefix(uri),
+        None => None,
+    };
+    match prefix {
+        Some(prefix) if !prefix.is_empty() => format!("{prefix}:{local}"),
+        _ => local.to_string(),
+    }
+}
+
+/// 转换为可修改的树；同时丢弃注释、处理指令、metadata 和编辑器私有内容
+fn convert(
+    node: roxmltree::Node,
+    inherited: &[(Option<String>, String)],
+    precision: usize,
+) -> Option<Element> {
+    let tag = node.tag_name();
+    if is_editor_namespace(tag.namespace())
+        || (tag.namespace() == Some(SVG_NS) && tag.name() == "metadata")
+    {
+        return None;
+    }
+
+    // 只输出本元素新引入的命名空间声明
+    let namespaces: Vec<(Option<String>, String)> = node
+        .namespaces()
+        .filter(|ns| ns.uri() != XML_NS)
+        .map(|ns| (ns.name().map(str::to_string), ns.uri().to_string()))
+        .collect();
+    let mut attributes = Vec::new();
+    for (prefix, uri) in &namespaces {
+        if inherited.contains(&(prefix.clone(), uri.clone()))
+            || EDITOR_NAMESPACES.contains(&uri.as_str())
+        {
+            continue;
+        }
+        let name = match prefix {
+            Some(prefix) => format!("xmlns:{prefix}"),
+            None => "xmlns".to_string(),
+        };
+        attributes.push((name, uri.clone()));
+    }
+
+    let local = tag.name();
+    for attribute in node.attributes() {
+        if is_editor_namespace(attribute.namespace()) {
+            continue;
+        }
+        let name = qualified_name(node, attribute.namespace(), attribute.name());
+        let value = match (local, attribute.namespace(), attribute.name()) {
+            ("path", None, "d") | ("polygon" | "polyline", None, "points") => {
+                round_numbers(attribute.value(), precision)
+                    .unwrap_or_else(|| attribute.value().to_string())
+            }
+            _ => attribute.value().to_string(),
+        };
+        attributes.push((name, value));
+    }
+
+    let keep_whitespace = TEXT_ELEMENTS.contains(&local);
+    let mut children = Vec::new();
+    for child in node.children() {
+        if child.is_element() {
+            if let Some(element) = convert(child, &namespaces, precision) {
+                children.push(Node::Element(element));
+            }
+        } else if let Some(text) = child.text().filter(|_| child.is_text()) {
+            if keep_whitespace || !text.trim().is_empty() {
+                children.push(Node::Text(text.to_string()));
+            }
+        }
+    }
+
+    Some(Element {
+        name: qualified_name(node, tag.namespace(), local),
+        attributes,
+        children,
+    })
+}
+
+/// 展开无属性的 g，把只有一个子元素的 g 的属性下移，并删除空的 g 和 defs
+fn collapse_groups(element: &mut Element) {
+    let children = std::mem::take(&mut element.children);
+    for child in children {
+        let Node::Element(mut child) = child else {
+            element.children.push(child);
+            continue;
+        };
+        collapse_groups(&mut child);
+
+        let removable = child.name == "g" || child.name == "defs";
+        let has_id = child.attributes.iter().any(|(name, _)| name == "id");
+        if removable && child.children.is_empty() && !has_id {
+            continue;
+        }
+
+        if child.name == "g" {
+            if child.attributes.is_empty() {
+                element.children.append(&mut child.children);
+                continue;
+            }
+            if let Some(only) = merge_single_child(&mut child) {
+                element.children.push(Node::Element(only));
+                continue;
+            }
+        }
+        element.children.push(Node::Element(child));
+    }
+}
+
+fn merge_single_child(group: &mut Element) -> Option<Element> {
+    let [Node::Element(only)] = group.children.as_slice() else {
+        return None;
+    };
+    // 带 id 的子元素可能被 use 引用，下移分组属性会改变引用处的渲染
+    if only.attributes.iter().any(|(name, _)| name == "id") {
+        return None;
+    }
+    let movable = group.attributes.iter().all(|(name, _)| {
+        !GROUP_ONLY_ATTRIBUTES.contains(&name.as_str())
+            && !name.starts_with("xmlns")
+            && (name == "transform" || !only.attributes.iter().any(|(other, _)| other == name))
+    });
+    if !movable {
+        return None;
+    }
+
+    let Some(Node::Element(mut only)) = group.children.pop() else {
+        return None;
+    };
+    for (name, value) in group.attributes.drain(..) {
+        match only.attributes.iter_mut().find(|(other, _)| *other == name) {
+            // 分组的变换先于子元素自身的变换
+            Some((_, existing)) => *existing = format!("{value} {existing}"),
+            None => only.attributes.push((name, value)),
+        }
+    }
+    Some(only)
+}
+
+fn prefix_of(name: &str) -> Option<&str> {
+    name.split_once(':').map(|(prefix, _)| prefix)
+}
+
+fn used_prefixes(element: &Element, used: &mut HashSet<String>) {
+    if let Some(prefix) = prefix_of(&element.name) {
+        used.insert(prefix.to_string());
+    }
+    for (name, _) in &element.attributes {
+        if let Some(prefix) = prefix_of(name).filter(|prefix| *prefix != "xmlns") {
+            used.insert(prefix.to_string());
+        }
+    }
+    for child in &element.children {
+        if let Node::Element(child) = child {
+            used_prefixes(child, used);
+        }
+    }
+}
+
+fn remove_unused_namespaces(element: &mut Element, used: &HashSet<String>) {
+    element
+        .attributes
+        .retain(|(name, _)| match name.strip_prefix("xmlns:") {
+            Some(prefix) => used.contains(prefix),
+            None => true,
+        });
+    for child in &mut element.children {
+        if let Node::Element(child) = child {
+            remove_unused_namespaces(child, used);
+        }
+    }
+}
+
+fn write_element(element: &Element, out: &mut String) {
+    out.push('<');
+    out.push_str(&element.name);
+    for (name, value) in &element.attributes {
+        out.push(' ');
+        out.push_str(name);
+        out.push_str("=\"");
+        escape(value, true, out);
+        out.push('"');
+    }
+
+    if element.children.is_empty() {
+        out.push_str("/>");
+        return;
+    }
+
+    out.push('>');
+    for child in &element.children {
+        match child {
+            Node::Element(child) => write_element(child, out),
+            Node::Text(text) => escape(text, false, out),
+        }
+    }
+    out.push_str("</");
+    out.push_str(&element.name);
+    out.push('>');
+}
+
+fn escape(text: &str, attribute: bool, out: &mut String) {
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' if attribute => out.push_str("&quot;"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+enum Token {
+    Command(char),
+    Number(f64),
+    Flag(char),
+}
+
+/// 舍入路径数据（或 points 列表）中的数字，并去掉不必要的分隔符；无法解析时返回 None
+fn round_numbers(data: &str, precision: usize) -> Option<String> {
+    let tokens = tokenize(data)?;
+
+    let mut out = String::with_capacity(data.len());
+    let mut previous: Option<String> = None;
+    for token in tokens {
+        match token {
+            Token::Command(command) => {
+                out.push(command);
+                previous = None;
+            }
+            Token::Flag(flag) => {
+                if previous.is_some() {
+                    out.push(' ');
+                }
+                out.push(flag);
+                previous = None;
+            }
+            Token::Number(value) => {
+                let number = format_number(value, precision);
+                if let Some(previous) = &previous {
+                    // 负号或第二个小数点本身就能分隔两个数字
+                    let needs_space = !(number.starts_with('-')
+                        || (number.starts_with('.') && previous.contains(['.', 'e'])));
+                    if needs_space {
+                        out.push(' ');
+                    }
+                }
+                out.push_str(&number);
+                previous = Some(number);
+            }
+        }
+    }
+    Some(out)
+}
+
+fn tokenize(data: &str) -> Option<Vec<Token>> {
+    let bytes = data.as_bytes();
+    let mut tokens = Vec::new();
+    let mut command = None;
+    let mut params = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if byte.is_ascii_whitespace() || byte == b',' {
+            i += 1;
+            continue;
+        }
+        if b"MmZzLlHhVvCcSsQqTtAa".contains(&byte) {
+            command = Some(byte.to_ascii_lowercase());
+            params = 0;
+            tokens.push(Token::Command(byte as char));
+            i += 1;
+            continue;
+        }
+
+        // 弧线命令的第 4、5 个参数是单字符标志位，可与后续数字相连
+        if command == Some(b'a') && matches!(params % 7, 3 | 4) {
+            if byte != b'0' && byte != b'1' {
+                return None;
+            }
+            tokens.push(Token::Flag(byte as char));
+            params += 1;
+            i += 1;
+            continue;
+        }
+
+        let end = number_end(bytes, i)?;
+        tokens.push(Token::Number(data[i..end].parse().ok()?));
+        params += 1;
+        i = end;
+    }
+    Some(tokens)
+}
+
+fn number_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let digits = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+
+    let mut i = start;
+    if i < bytes.len() && (bytes[i] == b'+' || bytes[i] == b'-') {
+        i += 1;
+    }
+    let integer_end = digits(i);
+    let mut end = integer_end;
+    if end < bytes.len() && bytes[end] == b'.' {
+        end = digits(end + 1);
+    }
+    // 至少要有一位数字
+    if end == i || (end == i + 1 && bytes[i] == b'.') {
+        return None;
+    }
+    if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+        let mut exponent = end + 1;
+        if exponent < bytes.len() && (bytes[exponent] == b'+' || bytes[exponent] == b'-') {
+            exponent += 1;
+        }
+        let exponent_end = digits(exponent);
+        if exponent_end > exponent {
+            end = exponent_end;
+        }
+    }
+    Some(end)
+}
+
+fn format_number(value: f64, precision: usize) -> String {
+    let mut number = format!("{value:.precision$}");
+    if number.contains('.') {
+        number = number
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string();
+    }
+    if number == "-0" {
+        return "0".to_string();
+    }
+    if let Some(rest) = number.strip_prefix("0.") {
+        return format!(".{rest}");
+    }
+    if let Some(rest) = number.strip_prefix("-0.") {
+        return format!("-.{rest}");
+    }
+    number
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minify(svg: &str) -> String {
+        String::from_utf8(compress_svg(svg.as_bytes(), 3).unwrap()).unwrap()
+    }
+
+    /// 输出重新解析后，命令与标志位不变，数字等于原值舍入后的结果
+    fn assert_round_trip(data: &str, precision: usize) -> String {
+        let rounded = round_numbers(data, precision).unwrap();
+        let before = tokenize(data).unwrap();
+        let after = tokenize(&rounded).unwrap();
+        assert_eq!(before.len(), after.len(), "{data} -> {rounded}");
+        let scale = 10f64.powi(precision as i32);
+        for (before, after) in before.iter().zip(&after) {
+            match (before, after) {
+                (Token::Command(a), Token::Command(b)) | (Token::Flag(a), Token::Flag(b)) => {
+                    assert_eq!(a, b, "{data} -> {rounded}")
+                }
+                (Token::Number(a), Token::Number(b)) => {
+                    assert!((a - b).abs() <= 0.5 / scale + 1e-12, "{data} -> {rounded}")
+                }
+                _ => panic!("token kind changed: {data} -> {rounded}"),
+            }
+        }
+        rounded
+    }
+
+    #[test]
+    fn path_data_round_trips() {
+        assert_eq!(
+            assert_round_trip("M10.123 20.456L-1e2,3.5e-1 l 1-2.5.5 z", 2),
+            "M10.12 20.46L-100 .35l1-2.5.5z"
+        );
+        assert_eq!(
+            assert_round_trip("M 0.0004,-0.0004 H 1E+2 v -1.5e-1 c 1,2 3,4 5,6", 3),
+            "M0 0H100v-.15c1 2 3 4 5 6"
+        );
+        // 标志位可与后续数字相连
+        assert_eq!(
+            assert_round_trip("a25,25 -30 0,1 50,-25 A 1 1 0 1150.5.5", 3),
+            "a25 25-30 0150-25A1 1 0 1150.5.5"
+        );
+        assert_eq!(assert_round_trip("10,20 30.25,-40", 1), "10 20 30.2-40");
+        assert!(round_numbers("M 1 2 L x", 3).is_none());
+        assert!(round_numbers("a 1 1 0 2 0 5 5", 3).is_none());
+    }
+
+    #[test]
+    fn editor_metadata_is_removed() {
+        let svg = r##"<?xml version="1.0"?>
+<!-- Created with Inkscape -->
+<svg xmlns="http://www.w3.org/2000/svg"
+     xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape"
+     xmlns:sodipodi="http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd"
+     xmlns:xlink="http://www.w3.org/1999/xlink"
+     xmlns:dc="http://purl.org/dc/elements/1.1/"
+     width="10" height="10" inkscape:version="1.3">
+  <sodipodi:namedview pagecolor="#ffffff"/>
+  <metadata><dc:title>icon</dc:title></metadata>
+  <g inkscape:label="Layer 1" inkscape:groupmode="layer">
+    <path d="M 0.0001,0 L 10,10" sodipodi:nodetypes="cc"/>
+  </g>
+</svg>"##;
+        assert_eq!(
+            minify(svg),
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><path d="M0 0L10 10"/></svg>"#
+        );
+    }
+
+    #[test]
+    fn groups_with_ids_are_kept() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"><g fill="red"><rect id="r" width="1" height="1"/></g><g id="g" fill="blue"><rect width="1" height="1"/></g><g fill="green"><rect width="2" height="2"/></g><use xlink:href="#r"/></svg>"##;
+        assert_eq!(
+            minify(svg),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"><g fill="red"><rect id="r" width="1" height="1"/></g><g id="g" fill="blue"><rect width="1" height="1"/></g><rect width="2" height="2" fill="green"/><use xlink:href="#r"/></svg>"##
+        );
+    }
+
+    #[cfg(feature = "svg-raster")]
+    #[test]
+    fn text_is_rendered_or_reported() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20"><text x="2" y="16" font-size="16">Hi</text></svg>"#;
+        match rasterize(svg, 0) {
+            Ok(image) => assert!(image.to_rgba8().pixels().any(|pixel| pixel[3] > 0)),
+            Err(err) => {
+                assert!(fonts().is_empty());
+                assert!(err.to_string().contains("no fonts"));
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::errors::CompressorError;
+use image::{DynamicImage, ImageBuffer};
+use std::io::Cursor;
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::ColorType;
+
+/// 多页 TIFF 的页数，无法解析时按单页处理
+pub fn page_count(data: &[u8]) -> u32 {
+    let Ok(mut decoder) = Decoder::new(Cursor::new(data)) else {
+        return 1;
+    };
+    let mut count = 1;
+    while decoder.more_images() && decoder.next_image().is_ok() {
+        count += 1;
+    }
+    count
+}
+
+/// 解码指定页（从 0 开始）；image 库的 TIFF 解码器只能读取第一页
+pub fn decode_page(data: &[u8], page: u32) -> Result<DynamicImage, CompressorError> {
+    let mut decoder = Decoder::new(Cursor::new(data))?;
+    decoder
+        .seek_to_image(page as usize)
+        .map_err(|_| CompressorError::DecodeError(format!("TIFF page {page} does not exist")))?;
+
+    let (width, height) = decoder.dimensions()?;
+    crate::decode::check_dimensions(width, height)?;
+    let color = decoder.colortype()?;
+    let image = match (color, decoder.read_image()?) {
+        (ColorType::Gray(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLuma8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLuma16)
+        }
+        (ColorType::GrayA(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLumaA8)
+        }
+        (ColorType::GrayA(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLumaA16)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgba8)
+        }
+        (ColorType::RGBA(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgba16)
+        }
+        _ => {
+            return Err(CompressorError::UnsupportedFormat(format!(
+                "TIFF color type {color:?}"
+            )))
+        }
+    };
+
+    image.ok_or_else(|| CompressorError::DecodeError("Invalid TIFF page size".to_string()))
+}
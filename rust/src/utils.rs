@@ -1,9 +1,18 @@
+use std::borrow::Cow;
+
 #[derive(Debug, Clone, Copy)]
 pub enum InputFormat {
     Png,
     Jpeg,
     Webp,
     Avif,
+    Gif,
+    Jxl,
+    /// 多尺寸 favicon
+    Ico,
+    Svg,
+    /// 自动选择体积最小的输出格式
+    Auto,
 }
 
 impl InputFormat {
@@ -13,11 +22,34 @@ impl InputFormat {
             "jpeg" | "jpg" => Some(InputFormat::Jpeg),
             "webp" => Some(InputFormat::Webp),
             "avif" => Some(InputFormat::Avif),
+            "gif" => Some(InputFormat::Gif),
+            "jxl" => Some(InputFormat::Jxl),
+            "ico" => Some(InputFormat::Ico),
+            "svg" => Some(InputFormat::Svg),
+            "auto" => Some(InputFormat::Auto),
             _ => None,
         }
     }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InputFormat::Png => "png",
+            InputFormat::Jpeg => "jpeg",
+            InputFormat::Webp => "webp",
+            InputFormat::Avif => "avif",
+            InputFormat::Gif => "gif",
+            InputFormat::Jxl => "jxl",
+            InputFormat::Ico => "ico",
+            InputFormat::Svg => "svg",
+            InputFormat::Auto => "auto",
+        }
+    }
 }
 
+const JXL_CONTAINER_SIGNATURE: [u8; 12] = [
+    0x00, 0x00, 0x00, 0x0c, b'J', b'X', b'L', b' ', 0x0d, 0x0a, 0x87, 0x0a,
+];
+
 pub fn detect_format(bytes: &[u8]) -> Option<&'static str> {
     if bytes.len() >= 8
         && bytes[0] == 0x89
@@ -43,13 +75,193 @@ pub fn detect_format(bytes: &[u8]) -> Option<&'static str> {
         return Some("webp");
     }
 
-    // AVIF 文件类型检测 (ftypavif / ftypavis)
-    if bytes.len() >= 28
-        && &bytes[4..8] == b"ftyp"
-        && (&bytes[8..12] == b"avif" || &bytes[8..12] == b"avis")
+    if bytes.len() >= 6 && (&bytes[0..6] == b"GIF87a" || &bytes[0..6] == b"GIF89a") {
+        return Some("gif");
+    }
+
+    if bytes.len() >= 14 && &bytes[0..2] == b"BM" {
+        return Some("bmp");
+    }
+
+    if bytes.len() >= 8 && (&bytes[0..4] == b"II*\0" || &bytes[0..4] == b"MM\0*") {
+        return Some("tiff");
+    }
+
+    // JPEG XL：裸码流以 FF 0A 开头，容器格式以 12 字节的 "JXL " 签名盒开头
+    if bytes.len() >= 2 && bytes[0] == 0xff && bytes[1] == 0x0a {
+        return Some("jxl");
+    }
+    if bytes.len() >= 12 && bytes[0..12] == JXL_CONTAINER_SIGNATURE {
+        return Some("jxl");
+    }
+
+    // ISOBMFF 容器 (ftyp)：AVIF 与 HEIF 共用，按主品牌和兼容品牌区分
+    if bytes.len() >= 16 && &bytes[4..8] == b"ftyp" {
+        let brands = ftyp_brands(bytes);
+        if brands.iter().any(|brand| brand == b"avif" || brand == b"avis") {
+            return Some("avif");
+        }
+        if brands.iter().any(|brand| HEIF_BRANDS.contains(brand)) {
+            return Some("heic");
+        }
+    }
+
+    if is_svg(bytes) {
+        return Some("svg");
+    }
+
+    // ICO 与 CUR（放在最后，签名较弱）：保留字 0，类型 1 (图标) 或 2 (光标)，至少一个目录项
+    if bytes.len() >= 6
+        && bytes[0..2] == [0, 0]
+        && (bytes[2..4] == [1, 0] || bytes[2..4] == [2, 0])
+        && bytes[4..6] != [0, 0]
     {
-        return Some("avif");
+        return Some("ico");
     }
 
     None
 }
+
+/// SVG 是文本格式：以 `<` 开头（可有 BOM 和空白），且开头部分出现 `<svg`
+fn is_svg(bytes: &[u8]) -> bool {
+    let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+    let start = bytes.iter().position(|byte| !byte.is_ascii_whitespace());
+    if start.map(|start| bytes[start]) != Some(b'<') {
+        return false;
+    }
+    let head = &bytes[..bytes.len().min(4096)];
+    head.windows(4).any(|window| window == b"<svg")
+}
+
+const HEIF_BRANDS: [[u8; 4]; 8] = [
+    *b"heic", *b"heix", *b"heim", *b"heis", *b"hevc", *b"hevx", *b"mif1", *b"msf1",
+];
+
+/// 读取 ftyp 盒中的主品牌和兼容品牌
+fn ftyp_brands(bytes: &[u8]) -> Vec<[u8; 4]> {
+    let size = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    let end = size.clamp(16, bytes.len());
+    // 主品牌 (8..12)，次版本号 (12..16)，之后为兼容品牌列表
+    std::iter::once(&bytes[8..12])
+        .chain(bytes[16..end].chunks_exact(4))
+        .filter_map(|brand| brand.try_into().ok())
+        .collect()
+}
+
+/// 检测动图（APNG 的 acTL 块、WebP 的 ANIM 块或多帧 GIF）
+pub fn is_animated(bytes: &[u8]) -> bool {
+    match detect_format(bytes) {
+        Some("png") => find_png_chunk(bytes, b"acTL").is_some(),
+        Some("webp") => find_riff_chunk(bytes, b"ANIM").is_some(),
+        Some("gif") => gif_frame_count(bytes) > 1,
+        _ => false,
+    }
+}
+
+/// 统计 GIF 帧数，超过两帧即停止
+fn gif_frame_count(bytes: &[u8]) -> usize {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let Ok(mut decoder) = options.read_info(std::io::Cursor::new(bytes)) else {
+        return 0;
+    };
+    let mut count = 0;
+    while count < 2 {
+        match decoder.next_frame_info() {
+            Ok(Some(_)) => count += 1,
+            _ => break,
+        }
+    }
+    count
+}
+
+/// 查找 PNG 块，返回块数据（不含长度、类型和 CRC）
+pub fn find_png_chunk<'a>(bytes: &'a [u8], name: &[u8; 4]) -> Option<&'a [u8]> {
+    let mut pos = 8;
+    while pos + 8 <= bytes.len() {
+        let len = u32::from_be_bytes(bytes[pos..pos + 4].try_into().ok()?) as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let start = pos + 8;
+        let end = start.checked_add(len)?;
+        if end > bytes.len() {
+            return None;
+        }
+        if kind == name {
+            return Some(&bytes[start..end]);
+        }
+        // IDAT 之后不会再出现动画控制块
+        if kind == b"IDAT" || kind == b"IEND" {
+            return None;
+        }
+        pos = end + 4;
+    }
+    None
+}
+
+/// 查找 RIFF (WebP) 块，返回块数据
+pub fn find_riff_chunk<'a>(bytes: &'a [u8], name: &[u8; 4]) -> Option<&'a [u8]> {
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let len = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().ok()?) as usize;
+        let start = pos + 8;
+        let end = start.checked_add(len)?;
+        if end > bytes.len() {
+            return None;
+        }
+        if &bytes[pos..pos + 4] == name {
+            return Some(&bytes[start..end]);
+        }
+        // 块按偶数字节对齐
+        pos = end + (len & 1);
+    }
+    None
+}
+
+/// 图片是否包含非不透明像素
+pub fn has_transparency(image: &image::DynamicImage) -> bool {
+    if !image.color().has_alpha() {
+        return false;
+    }
+    let rgba = rgba8(image);
+    let transparent = rgba.pixels().any(|p| p[3] < 255);
+    recycle_image(rgba);
+    transparent
+}
+
+/// 已是 RGBA8 时直接借用；RGB8 转换到复用的缓冲区，用完交给 recycle_image 归还
+pub fn rgba8(image: &image::DynamicImage) -> Cow<'_, image::RgbaImage> {
+    match image {
+        image::DynamicImage::ImageRgba8(rgba) => Cow::Borrowed(rgba),
+        image::DynamicImage::ImageRgb8(rgb) => {
+            let mut buffer = crate::memory::take_buffer();
+            buffer.reserve(rgb.as_raw().len() / 3 * 4);
+            for pixel in rgb.as_raw().chunks_exact(3) {
+                buffer.extend_from_slice(&[pixel[0], pixel[1], pixel[2], 255]);
+            }
+            let rgba = image::RgbaImage::from_raw(rgb.width(), rgb.height(), buffer)
+                .expect("buffer matches dimensions");
+            Cow::Owned(rgba)
+        }
+        _ => Cow::Owned(image.to_rgba8()),
+    }
+}
+
+/// 已是 RGB8 时直接借用
+pub fn rgb8(image: &image::DynamicImage) -> Cow<'_, image::RgbImage> {
+    match image {
+        image::DynamicImage::ImageRgb8(rgb) => Cow::Borrowed(rgb),
+        _ => Cow::Owned(image.to_rgb8()),
+    }
+}
+
+/// 把临时转换出的像素缓冲区交回缓冲池
+pub fn recycle_image<P: image::Pixel<Subpixel = u8>>(image: Cow<'_, image::ImageBuffer<P, Vec<u8>>>) {
+    if let Cow::Owned(image) = image {
+        crate::memory::recycle(image.into_raw());
+    }
+}
+
+/// 把 RGBA8 字节按 imagequant / ravif 需要的像素类型重新解释，不复制
+pub fn as_rgba_pixels(rgba: &image::RgbaImage) -> &[rgb::RGBA8] {
+    rgb::FromSlice::as_rgba(rgba.as_raw().as_slice())
+}
@@ -0,0 +1,251 @@
+use crate::alpha::Background;
+use crate::errors::CompressorError;
+use crate::resize::{apply_resize, ResizeOptions};
+
+/// 未指定宽度时生成的断点
+pub const DEFAULT_WIDTHS: [u32; 4] = [320, 640, 1280, 1920];
+
+/// 响应式图片的一个变体
+pub struct Variant {
+    pub format: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl Variant {
+    /// 文件名形如 `hero-640.webp`
+    pub fn file_name(&self, base_name: &str) -> String {
+        format!("{base_name}-{}.{}", self.width, extension(self.format))
+    }
+}
+
+/// 未指定格式时按已启用的编码器生成，优先级从高到低
+pub fn default_formats() -> Vec<String> {
+    let mut formats = Vec::new();
+    if cfg!(feature = "avif") {
+        formats.push("avif".to_string());
+    }
+    if cfg!(feature = "webp") {
+        formats.push("webp".to_string());
+    }
+    formats.push("jpeg".to_string());
+    formats
+}
+
+/// 只解码一次，按每个宽度缩放后编码为每种格式。超过原图的宽度按原图宽度生成一次，不放大
+#[allow(clippy::too_many_arguments)]
+pub fn generate_variants(
+    data: &[u8],
+    widths: &[u32],
+    formats: &[String],
+    quality: u8,
+    dithering: bool,
+    progressive: bool,
+    auto_rotate: bool,
+    background: Background,
+) -> Result<Vec<Variant>, CompressorError> {
+    let image = crate::decode::load_oriented(data, auto_rotate)?;
+
+    let mut widths: Vec<u32> = widths
+        .iter()
+        .filter(|&&width| width > 0)
+        .map(|&width| width.min(image.width()))
+        .collect();
+    widths.sort_unstable();
+    widths.dedup();
+
+    let mut unique: Vec<&'static str> = Vec::new();
+    for format in formats {
+        let format = normalize_format(format)?;
+        if !unique.contains(&format) {
+            unique.push(format);
+        }
+    }
+
+    // 每个宽度独立缩放和编码，启用 threads 时并行
+    let per_width = crate::parallel::map(&widths, |&width| {
+        let resized = apply_resize(image.clone(), &ResizeOptions::new("maxWidth", width))?;
+        unique
+            .iter()
+            .map(|&format| {
+                Ok(Variant {
+                    format,
+                    width: resized.width(),
+                    height: resized.height(),
+                    data: encode(
+                        &resized,
+                        format,
+                        quality,
+                        dithering,
+                        progressive,
+                        background,
+                    )?,
+                })
+            })
+            .collect::<Result<Vec<_>, CompressorError>>()
+    });
+
+    let mut variants = Vec::with_capacity(widths.len() * unique.len());
+    for group in per_width {
+        variants.extend(group?);
+    }
+    Ok(variants)
+}
+
+/// 生成可直接粘贴的 <picture> 片段：现代格式放在 <source>，JPEG/PNG 作为 <img> 回退
+pub fn picture_html(variants: &[Variant], base_name: &str, alt: &str, sizes: &str) -> String {
+    let mut formats: Vec<&str> = Vec::new();
+    for variant in variants {
+        if !formats.contains(&variant.format) {
+            formats.push(variant.format);
+        }
+    }
+    let fallback = formats
+        .iter()
+        .rev()
+        .find(|format| matches!(**format, "jpeg" | "png"))
+        .or(formats.last())
+        .copied();
+
+    let srcset = |format: &str| {
+        variants
+            .iter()
+            .filter(|variant| variant.format == format)
+            .map(|variant| format!("{} {}w", url(&variant.file_name(base_name)), variant.width))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut html = String::from("<picture>\n");
+    for &format in formats.iter().filter(|format| Some(**format) != fallback) {
+        html.push_str(&format!(
+            "  <source type=\"{}\" srcset=\"{}\" sizes=\"{}\">\n",
+            mime_type(format),
+            srcset(format),
+            escape_attribute(sizes)
+        ));
+    }
+
+    // 回退图片取最大的一张，并写明宽高以避免布局偏移
+    let largest = variants
+        .iter()
+        .filter(|variant| Some(variant.format) == fallback)
+        .max_by_key(|variant| variant.width);
+    if let (Some(format), Some(largest)) = (fallback, largest) {
+        html.push_str(&format!(
+            "  <img src=\"{}\" srcset=\"{}\" sizes=\"{}\" width=\"{}\" height=\"{}\" alt=\"{}\" loading=\"lazy\" decoding=\"async\">\n",
+            url(&largest.file_name(base_name)),
+            srcset(format),
+            escape_attribute(sizes),
+            largest.width,
+            largest.height,
+            escape_attribute(alt)
+        ));
+    }
+    html.push_str("</picture>");
+    html
+}
+
+fn normalize_format(format: &str) -> Result<&'static str, CompressorError> {
+    match format {
+        "avif" => Ok("avif"),
+        "webp" => Ok("webp"),
+        "jpeg" | "jpg" => Ok("jpeg"),
+        "png" => Ok("png"),
+        _ => Err(CompressorError::UnsupportedFormat(format.to_string())),
+    }
+}
+
+fn extension(format: &str) -> &'static str {
+    match format {
+        "avif" => "avif",
+        "webp" => "webp",
+        "png" => "png",
+        _ => "jpg",
+    }
+}
+
+fn mime_type(format: &str) -> &'static str {
+    match format {
+        "avif" => "image/avif",
+        "webp" => "image/webp",
+        "png" => "image/png",
+        _ => "image/jpeg",
+    }
+}
+
+/// 文件名用作 URL：空格、逗号会破坏 srcset，引号和尖括号会破坏属性，
+/// 因此除 URL 安全字符外都按 UTF-8 字节百分号编码，再按属性值转义
+fn url(file_name: &str) -> String {
+    let mut encoded = String::with_capacity(file_name.len());
+    for byte in file_name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    escape_attribute(&encoded)
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+fn encode(
+    image: &image::DynamicImage,
+    format: &str,
+    quality: u8,
+    dithering: bool,
+    progressive: bool,
+    background: Background,
+) -> Result<Vec<u8>, CompressorError> {
+    match format {
+        "avif" => {
+            #[cfg(feature = "avif")]
+            {
+                crate::avif::encode_avif(image, quality)
+            }
+            #[cfg(not(feature = "avif"))]
+            {
+                Err(CompressorError::EncodeError(
+                    "AVIF encoding requires avif feature".to_string(),
+                ))
+            }
+        }
+        "webp" => {
+            #[cfg(feature = "webp")]
+            {
+                crate::webp::encode_webp(image, quality)
+            }
+            #[cfg(not(feature = "webp"))]
+            {
+                Err(CompressorError::WebpNotEnabled)
+            }
+        }
+        "png" => crate::png::encode_png(image, quality, dithering, false),
+        _ => crate::jpeg::encode_jpeg(image, quality, progressive, background),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picture_html_encodes_base_name() {
+        let variants = [Variant {
+            format: "jpeg",
+            width: 640,
+            height: 480,
+            data: Vec::new(),
+        }];
+        let html = picture_html(&variants, "a\" onerror=\"x<b>, c", "", "100vw");
+        assert!(html.contains("src=\"a%22%20onerror%3D%22x%3Cb%3E%2C%20c-640.jpg\""));
+        assert!(!html.contains("onerror=\""));
+    }
+}
@@ -1,11 +1,179 @@
+use crate::alpha::AlphaCleanup;
+use crate::animation::{decode_animation, Animation};
 use crate::errors::CompressorError;
+use crate::resize::{apply_resize, ResizeOptions};
+use crate::utils::is_animated;
 
-pub fn compress_webp(data: &[u8], quality: u8) -> Result<Vec<u8>, CompressorError> {
-    let image = image::load_from_memory(data)?;
-    let rgba = image.to_rgba8();
+pub fn compress_webp(
+    data: &[u8],
+    quality: u8,
+    resize: &ResizeOptions,
+    alpha: AlphaCleanup,
+) -> Result<Vec<u8>, CompressorError> {
+    // 动图逐帧清理透明像素并缩放，与静态图片的处理顺序一致
+    if is_animated(data) {
+        let animation = decode_animation(data)?.clean_alpha(alpha).resize(resize)?;
+        return encode_animated_webp(&animation, quality);
+    }
+
+    let image = crate::decode::load_image(data)?;
+    let image = crate::alpha::clean(image, alpha);
+    let image = apply_resize(image, resize)?;
+    encode_webp(&image, quality)
+}
+
+pub fn encode_webp(image: &image::DynamicImage, quality: u8) -> Result<Vec<u8>, CompressorError> {
+    // 没有 alpha 通道时按 RGB 编码，不写 ALPH 块
+    if !image.color().has_alpha() {
+        let rgb = crate::utils::rgb8(image);
+        let (width, height) = rgb.dimensions();
+
+        crate::progress::report("encode", 0.0)?;
+        let webp = webp::Encoder::from_rgb(rgb.as_raw(), width, height)
+            .encode(quality as f32)
+            .to_vec();
+        crate::utils::recycle_image(rgb);
+        crate::progress::report("encode", 1.0)?;
+        return Ok(webp);
+    }
+
+    let rgba = crate::utils::rgba8(image);
     let (width, height) = rgba.dimensions();
 
-    let encoder = webp::Encoder::from_rgba(rgba.as_raw(), width as u32, height as u32);
-    let webp = encoder.encode(quality as f32);
-    Ok(webp.to_vec())
+    crate::progress::report("encode", 0.0)?;
+    let encoder = webp::Encoder::from_rgba(rgba.as_raw(), width, height);
+    let webp = encoder.encode(quality as f32).to_vec();
+    crate::utils::recycle_image(rgba);
+    crate::progress::report("encode", 1.0)?;
+    Ok(webp)
+}
+
+pub fn encode_animated_webp(animation: &Animation, quality: u8) -> Result<Vec<u8>, CompressorError> {
+    let mut config = webp::WebPConfig::new()
+        .map_err(|_| CompressorError::EncodeError("Failed to init WebP config".to_string()))?;
+    config.quality = quality as f32;
+
+    let mut encoder = webp::AnimEncoder::new(animation.width, animation.height, &config);
+    encoder.set_loop_count(animation.loop_count as i32);
+
+    // libwebp 使用每帧的起始时间戳
+    let mut timestamp = 0i32;
+    for frame in &animation.frames {
+        encoder.add_frame(webp::AnimFrame::from_rgba(
+            frame.image.as_raw(),
+            frame.image.width(),
+            frame.image.height(),
+            timestamp,
+        ));
+        timestamp = timestamp.saturating_add(frame.delay_ms as i32);
+    }
+
+    crate::progress::report("encode", 0.0)?;
+    let webp = encoder
+        .try_encode()
+        .map_err(|err| CompressorError::EncodeError(format!("{err:?}")))?;
+    let mut out = webp.to_vec();
+    if let Some(last) = animation.frames.last() {
+        set_last_frame_duration(&mut out, last.delay_ms);
+    }
+    Ok(out)
+}
+
+/// webp 库结束编码时传入的时间戳为 0，最后一帧的时长会被错误推算，这里直接改写最后一个 ANMF 块
+fn set_last_frame_duration(webp: &mut [u8], delay_ms: u32) {
+    let mut pos = 12;
+    let mut last = None;
+    while pos + 8 <= webp.len() {
+        let len = u32::from_le_bytes([webp[pos + 4], webp[pos + 5], webp[pos + 6], webp[pos + 7]])
+            as usize;
+        if &webp[pos..pos + 4] == b"ANMF" && len >= 16 {
+            last = Some(pos + 8);
+        }
+        pos += 8 + len + (len & 1);
+    }
+    // ANMF 负载：X、Y、宽、高各 3 字节，随后是 3 字节时长
+    if let Some(start) = last.filter(|start| start + 15 <= webp.len()) {
+        let duration = delay_ms.min(0xff_ffff).to_le_bytes();
+        webp[start + 12..start + 15].copy_from_slice(&duration[..3]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::Frame;
+
+    fn animation(delays: &[u32]) -> Animation {
+        let frames = delays
+            .iter()
+            .enumerate()
+            .map(|(i, &delay_ms)| Frame {
+                image: image::RgbaImage::from_fn(32, 24, |x, y| {
+                    // 左侧一列透明，透明像素带有残留颜色
+                    let alpha = if x < 4 { 0 } else { 255 };
+                    image::Rgba([(x * 8) as u8, (y * 10) as u8, (i * 60) as u8, alpha])
+                }),
+                delay_ms,
+            })
+            .collect();
+        Animation {
+            width: 32,
+            height: 24,
+            loop_count: 2,
+            frames,
+        }
+    }
+
+    /// 依次读取每个 ANMF 块的时长
+    fn frame_durations(webp: &[u8]) -> Vec<u32> {
+        let mut durations = Vec::new();
+        let mut pos = 12;
+        while pos + 8 <= webp.len() {
+            let len = u32::from_le_bytes(webp[pos + 4..pos + 8].try_into().unwrap()) as usize;
+            if &webp[pos..pos + 4] == b"ANMF" {
+                let payload = &webp[pos + 8..pos + 8 + len];
+                durations.push(u32::from_le_bytes([
+                    payload[12],
+                    payload[13],
+                    payload[14],
+                    0,
+                ]));
+            }
+            pos += 8 + len + (len & 1);
+        }
+        durations
+    }
+
+    #[test]
+    fn every_frame_keeps_its_duration() {
+        let delays = [100, 250, 70, 1200];
+        let output = encode_animated_webp(&animation(&delays), 75).unwrap();
+        assert_eq!(frame_durations(&output), delays);
+
+        let decoded = decode_animation(&output).unwrap();
+        assert_eq!(decoded.loop_count, 2);
+        let decoded: Vec<u32> = decoded.frames.iter().map(|frame| frame.delay_ms).collect();
+        assert_eq!(decoded, delays);
+    }
+
+    #[test]
+    fn animated_input_is_cleaned_and_resized_per_frame() {
+        let input = encode_animated_webp(&animation(&[100, 200]), 100).unwrap();
+        let output = compress_webp(
+            &input,
+            75,
+            &ResizeOptions::new("percentage", 50),
+            AlphaCleanup::Zero,
+        )
+        .unwrap();
+
+        let decoded = decode_animation(&output).unwrap();
+        assert_eq!((decoded.width, decoded.height), (16, 12));
+        assert_eq!(decoded.frames.len(), 2);
+        assert_eq!(frame_durations(&output), [100, 200]);
+        for frame in &decoded.frames {
+            assert_eq!(frame.image.dimensions(), (16, 12));
+            assert_eq!(frame.image.get_pixel(0, 6)[3], 0);
+        }
+    }
 }
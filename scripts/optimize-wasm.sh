@@ -6,7 +6,7 @@ if ! command -v wasm-opt >/dev/null 2>&1; then
   exit 1
 fi
 
-for wasm in web/pkg/*.wasm; do
+for wasm in web/pkg/*.wasm web/pkg-threads/*.wasm; do
   [ -f "$wasm" ] || continue
   wasm-opt -Oz "$wasm" -o "$wasm"
 done
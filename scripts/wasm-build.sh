@@ -82,4 +82,12 @@ elif command -v xcrun >/dev/null 2>&1; then
   export CFLAGS_wasm32_unknown_unknown="${CFLAGS_wasm32_unknown_unknown:-} -isysroot ${SDKROOT}"
 fi
 
-wasm-pack build rust --target web --out-dir ../web/pkg --release --no-opt --features avif,webp
+wasm-pack build rust --target web --out-dir ../web/pkg --release --no-opt --features avif,webp,jxl
+
+# 多线程构建需要 nightly 工具链，C 代码也要启用 atomics 才能链接共享内存
+if [[ "${WASM_THREADS:-0}" == "1" ]]; then
+  CFLAGS_wasm32_unknown_unknown="${CFLAGS_wasm32_unknown_unknown:-} -matomics -mbulk-memory" \
+  RUSTFLAGS="-C target-feature=+atomics,+bulk-memory" \
+    rustup run nightly wasm-pack build rust --target web --out-dir ../web/pkg-threads --release --no-opt \
+    --features avif,webp,jxl,threads -- -Z build-std=panic_abort,std
+fi
@@ -1,5 +1,11 @@
 import { defineConfig } from 'vite';
 
+// 跨源隔离后才能使用 SharedArrayBuffer（多线程 wasm）
+const crossOriginIsolation = {
+  'Cross-Origin-Opener-Policy': 'same-origin',
+  'Cross-Origin-Embedder-Policy': 'require-corp',
+};
+
 export default defineConfig({
   root: 'web',
   publicDir: 'pwa',
@@ -9,4 +15,14 @@ export default defineConfig({
     emptyOutDir: true,
     target: 'es2020',
   },
+  // 多线程构建会按需加载模块并在 worker 内再创建 worker
+  worker: {
+    format: 'es',
+  },
+  server: {
+    headers: crossOriginIsolation,
+  },
+  preview: {
+    headers: crossOriginIsolation,
+  },
 });
@@ -25,6 +25,7 @@ interface Translations {
   // Dropzone
   dropFilesTitle: string
   dropFilesSubtitle: string
+  jxlOutputUnavailable: string
 
   // Controls
   compressionMode: string
@@ -42,15 +43,19 @@ interface Translations {
   resizeFixed: string
   presetMode: string
   presetCustom: string
-  presetSocial: string
-  presetWeb: string
-  presetArchive: string
+  presetWebPhoto: string
+  presetThumbnail: string
+  presetLossless: string
+  presetMaxCompression: string
+  presetScreenshot: string
+  presetPrint: string
   quality: string
   ditherPNGs: string
   progressiveJPEG: string
   convertPNGToWebP: string
   convertToAvif: string
   pngTruecolor: string
+  cleanAlpha: string
   autoRotate: string
   stripExif: string
   downloadAll: string
@@ -122,6 +127,8 @@ const translations: Record<Language, Translations> = {
     aborted: 'Aborted',
     dropFilesTitle: 'Drop files to compress',
     dropFilesSubtitle: 'or click to select images',
+    jxlOutputUnavailable:
+      'JPEG XL images are saved as JPEG; JXL output is not available in the browser',
     compressionMode: 'Compression Mode',
     modeQuality: 'Quality',
     modeTargetSize: 'Target Size',
@@ -137,15 +144,19 @@ const translations: Record<Language, Translations> = {
     resizeFixed: 'Fixed Width',
     presetMode: 'Preset Mode',
     presetCustom: 'Custom',
-    presetSocial: 'Social',
-    presetWeb: 'Web',
-    presetArchive: 'Archive',
+    presetWebPhoto: 'Web photo',
+    presetThumbnail: 'Thumbnail',
+    presetLossless: 'Lossless',
+    presetMaxCompression: 'Max compression',
+    presetScreenshot: 'Screenshot',
+    presetPrint: 'Print',
     quality: 'Quality',
     ditherPNGs: 'Dither PNGs',
     progressiveJPEG: 'Progressive JPEG',
     convertPNGToWebP: 'Convert PNG to WebP',
-    convertToAvif: 'Convert to AVIF',
+    convertToAvif: 'Convert still images to AVIF',
     pngTruecolor: 'Keep PNG truecolor',
+    cleanAlpha: 'Clean transparent pixels',
     autoRotate: 'Auto-rotate (EXIF)',
     stripExif: 'Remove EXIF metadata',
     downloadAll: 'Download all',
@@ -202,6 +213,7 @@ const translations: Record<Language, Translations> = {
     aborted: '已取消',
     dropFilesTitle: '拖放文件进行压缩',
     dropFilesSubtitle: '或点击选择图片',
+    jxlOutputUnavailable: 'JPEG XL 图片会保存为 JPEG，浏览器中无法输出 JXL',
     compressionMode: '压缩模式',
     modeQuality: '质量模式',
     modeTargetSize: '目标大小',
@@ -217,15 +229,19 @@ const translations: Record<Language, Translations> = {
     resizeFixed: '固定宽度',
     presetMode: '预设模式',
     presetCustom: '自定义',
-    presetSocial: '社交分享',
-    presetWeb: 'Web 发布',
-    presetArchive: '长期存档',
+    presetWebPhoto: '网页照片',
+    presetThumbnail: '缩略图',
+    presetLossless: '无损',
+    presetMaxCompression: '极限压缩',
+    presetScreenshot: '截图',
+    presetPrint: '印刷',
     quality: '质量',
     ditherPNGs: 'PNG 抖动',
     progressiveJPEG: '渐进式 JPEG',
     convertPNGToWebP: '将 PNG 转换为 WebP',
-    convertToAvif: '转换为 AVIF',
+    convertToAvif: '静态图片转换为 AVIF',
     pngTruecolor: '保留 PNG 真彩',
+    cleanAlpha: '清理透明像素',
     autoRotate: 'EXIF 自动旋转',
     stripExif: '清除 EXIF 元数据',
     downloadAll: '全部下载',
@@ -15,13 +15,20 @@ const worker = new Worker(new URL('./worker.ts', import.meta.url), {
   type: 'module',
 })
 
+// 跨源隔离时通过共享内存通知 worker 中止正在进行的压缩，否则只能在文件之间取消
+const cancelFlag = self.crossOriginIsolated
+  ? new Int32Array(new SharedArrayBuffer(4))
+  : undefined
+
 type WorkerMessage =
-  | { type: 'ready'; version: string }
+  | { type: 'ready'; version: string; presets: EnginePreset[] }
   | { type: 'started'; id: string }
   | { type: 'completed'; id: string; output: Uint8Array; outputFormat: string; quality?: number }
   | { type: 'error'; id: string; message: string }
   | { type: 'aborted' }
   | { type: 'skipped'; id: string }
+  | { type: 'progress'; id: string; stage: string; fraction: number; iteration?: number }
+  | { type: 'cancelled'; id: string }
 
 interface OutputItem {
   data: Uint8Array
@@ -46,6 +53,10 @@ const mimeByFormat: Record<string, string> = {
   jpg: 'image/jpeg',
   webp: 'image/webp',
   avif: 'image/avif',
+  gif: 'image/gif',
+  jxl: 'image/jxl',
+  ico: 'image/x-icon',
+  svg: 'image/svg+xml',
 }
 
 const extensionByFormat: Record<string, string> = {
@@ -54,12 +65,19 @@ const extensionByFormat: Record<string, string> = {
   jpg: 'jpg',
   webp: 'webp',
   avif: 'avif',
+  gif: 'gif',
+  jxl: 'jxl',
+  ico: 'ico',
+  svg: 'svg',
 }
 
 worker.onmessage = (event: MessageEvent<WorkerMessage>) => {
   const message = event.data
   if (message.type === 'ready') {
     currentEngineVersion = `v${message.version}`
+    for (const preset of message.presets) {
+      presets.set(preset.name, preset)
+    }
     currentStatus = 'ready'
     elements.engineVersion.textContent = currentEngineVersion
     elements.status.textContent = t().ready
@@ -85,6 +103,20 @@ worker.onmessage = (event: MessageEvent<WorkerMessage>) => {
     return
   }
 
+  if (message.type === 'progress') {
+    item.progress = message.fraction
+    item.iteration = message.iteration
+    updateRow(item)
+    return
+  }
+
+  if (message.type === 'cancelled') {
+    item.status = 'aborted'
+    updateRow(item)
+    updateStats()
+    return
+  }
+
   if (message.type === 'completed') {
     item.status = 'done'
     item.outputSize = message.output.byteLength
@@ -244,8 +276,10 @@ async function enqueueFiles(fileList: FileList | File[]) {
             convertToWebp: elements.convertWebpInput.checked,
             convertToAvif: elements.convertAvifInput?.checked ?? false,
             pngTruecolor: elements.pngTruecolorInput.checked,
+            cleanAlpha: elements.cleanAlphaInput.checked,
             autoRotate: elements.autoRotateInput.checked,
             stripExif: elements.stripExifInput.checked,
+            preset: currentPreset !== 'custom' ? currentPreset : undefined,
             targetSize: currentCompressionMode === 'targetSize' ? getTargetSizeBytes() : undefined,
             resizeMode: elements.resizeEnabled.checked ? elements.resizeMode.value : undefined,
             resizeValue: elements.resizeEnabled.checked ? parseInt(elements.resizeValue.value) : undefined,
@@ -374,8 +408,10 @@ async function reprocessCompletedFiles() {
             convertToWebp: elements.convertWebpInput.checked,
             convertToAvif: elements.convertAvifInput?.checked ?? false,
             pngTruecolor: elements.pngTruecolorInput.checked,
+            cleanAlpha: elements.cleanAlphaInput.checked,
             autoRotate: elements.autoRotateInput.checked,
             stripExif: elements.stripExifInput.checked,
+            preset: currentPreset !== 'custom' ? currentPreset : undefined,
             resizeMode: elements.resizeEnabled.checked ? elements.resizeMode.value : undefined,
             resizeValue: elements.resizeEnabled.checked ? parseInt(elements.resizeValue.value) : undefined,
           },
@@ -389,77 +425,46 @@ async function reprocessCompletedFiles() {
 }
 
 // 预设模式
-type PresetMode = 'custom' | 'social' | 'web' | 'archive'
+// 预设定义在 Rust 中（list_presets），界面、命令行与库调用对同一预设得到相同结果
+type PresetMode = string // 'custom' 或预设名
 
-interface PresetConfig {
+interface EnginePreset {
+  name: string
+  format: string
   quality: number
-  dithering: boolean
-  progressive: boolean
-  convertToWebp: boolean
-  convertToAvif: boolean
-  pngTruecolor: boolean
-  autoRotate: boolean
-  stripExif: boolean
-  getDescription: () => string
+  options: {
+    dithering?: boolean
+    progressive?: boolean
+    png_truecolor?: boolean
+    alpha_cleanup?: string
+    auto_rotate?: boolean
+    strip_exif?: boolean
+  }
 }
 
-const presets: Record<PresetMode, PresetConfig> = {
-  custom: {
-    quality: 75,
-    dithering: true,
-    progressive: true,
-    convertToWebp: false,
-    convertToAvif: false,
-    pngTruecolor: false,
-    autoRotate: true,
-    stripExif: true,
-    getDescription: () => '',
-  },
-  social: {
-    quality: 82,
-    dithering: true,
-    progressive: true,
-    convertToWebp: false,
-    convertToAvif: false,
-    pngTruecolor: false,
-    autoRotate: true,
-    stripExif: true,
-    getDescription: () => {
-      const tr = t()
-      const isZh = getLanguage() === 'zh'
-      return tr.presetSocial + ' · ' + (isZh ? '高质量 82' : 'High quality 82')
-    },
-  },
-  web: {
-    quality: 70,
-    dithering: true,
-    progressive: true,
-    convertToWebp: true,
-    convertToAvif: false,
-    pngTruecolor: false,
-    autoRotate: true,
-    stripExif: true,
-    getDescription: () => {
-      const tr = t()
-      const isZh = getLanguage() === 'zh'
-      return tr.presetWeb + ' · ' + (isZh ? '平衡质量与大小 70' : 'Balanced 70')
-    },
-  },
-  archive: {
-    quality: 55,
-    dithering: false,
-    progressive: false,
-    convertToWebp: false,
-    convertToAvif: false,
-    pngTruecolor: false,
-    autoRotate: true,
-    stripExif: true,
-    getDescription: () => {
-      const tr = t()
-      const isZh = getLanguage() === 'zh'
-      return tr.presetArchive + ' · ' + (isZh ? '最小文件大小 55' : 'Smallest size 55')
-    },
-  },
+type PresetLabel =
+  | 'presetWebPhoto'
+  | 'presetThumbnail'
+  | 'presetLossless'
+  | 'presetMaxCompression'
+  | 'presetScreenshot'
+  | 'presetPrint'
+
+const presetLabels: Record<string, PresetLabel | undefined> = {
+  'web-photo': 'presetWebPhoto',
+  thumbnail: 'presetThumbnail',
+  lossless: 'presetLossless',
+  'max-compression': 'presetMaxCompression',
+  screenshot: 'presetScreenshot',
+  print: 'presetPrint',
+}
+
+// worker 就绪后填充
+const presets = new Map<string, EnginePreset>()
+
+function describePreset(preset: EnginePreset): string {
+  const label = presetLabels[preset.name]
+  return `${label ? t()[label] : preset.name} · ${preset.format.toUpperCase()} ${preset.quality}`
 }
 
 let currentPreset: PresetMode = 'custom'
@@ -470,7 +475,7 @@ function setupPresets() {
 
   const applyPreset = (preset: PresetMode) => {
     currentPreset = preset
-    const config = presets[preset]
+    const config = presets.get(preset)
 
     // 更新UI
     presetButtons.forEach((btn) => {
@@ -482,26 +487,25 @@ function setupPresets() {
     })
 
     // 更新描述
-    const description = config.getDescription()
-    if (presetDescription && description) {
-      presetDescription.textContent = description
-    } else if (presetDescription) {
-      presetDescription.textContent = ''
+    if (presetDescription) {
+      presetDescription.textContent = config ? describePreset(config) : ''
     }
 
-    // 应用预设值（custom 模式不修改）
-    if (preset !== 'custom') {
+    // 控件只用于展示，压缩时 worker 直接使用预设的完整选项（custom 模式不修改）
+    if (config) {
+      const options = config.options
       elements.qualityInput.value = config.quality.toString()
       elements.qualityValue.textContent = config.quality.toString()
-      elements.ditherInput.checked = config.dithering
-      elements.progressiveInput.checked = config.progressive
-      elements.convertWebpInput.checked = config.convertToWebp
+      elements.ditherInput.checked = options.dithering ?? true
+      elements.progressiveInput.checked = options.progressive ?? true
+      elements.convertWebpInput.checked = false
       if (elements.convertAvifInput) {
-        elements.convertAvifInput.checked = config.convertToAvif
+        elements.convertAvifInput.checked = false
       }
-      elements.pngTruecolorInput.checked = config.pngTruecolor
-      elements.autoRotateInput.checked = config.autoRotate
-      elements.stripExifInput.checked = config.stripExif
+      elements.pngTruecolorInput.checked = options.png_truecolor ?? false
+      elements.cleanAlphaInput.checked = (options.alpha_cleanup ?? 'none') !== 'none'
+      elements.autoRotateInput.checked = options.auto_rotate ?? true
+      elements.stripExifInput.checked = options.strip_exif ?? true
     }
 
     // 如果有已完成的文件，提示是否重新处理
@@ -527,7 +531,7 @@ function setupPresets() {
     if (btn instanceof HTMLElement && btn.dataset.preset) {
       btn.addEventListener('click', () => {
         const preset = btn.dataset.preset as PresetMode
-        if (preset in presets) {
+        if (preset === 'custom' || presets.has(preset)) {
           applyPreset(preset)
         }
       })
@@ -717,6 +721,7 @@ function setupControls() {
     elements.convertAvifInput.addEventListener('change', handleOptionChange)
   }
   elements.pngTruecolorInput.addEventListener('change', handleOptionChange)
+  elements.cleanAlphaInput.addEventListener('change', handleOptionChange)
   elements.autoRotateInput.addEventListener('change', handleOptionChange)
   elements.stripExifInput.addEventListener('change', handleOptionChange)
   // quality 滑块在拖动时也会触发，所以只在值改变时提示
@@ -730,6 +735,7 @@ function setupControls() {
 
   elements.downloadAll.addEventListener('click', downloadAll)
   elements.cancel.addEventListener('click', () => {
+    if (cancelFlag) Atomics.store(cancelFlag, 0, 1)
     worker.postMessage({ type: 'abort' })
     for (const item of state.items.values()) {
       if (item.status === 'queued') {
@@ -1058,9 +1064,9 @@ function setupI18n() {
 
     // 更新预设模式描述
     const presetDescription = document.getElementById('presetDescription') as HTMLElement | null
-    if (presetDescription && currentPreset !== 'custom') {
-      const config = presets[currentPreset]
-      presetDescription.textContent = config.getDescription()
+    const config = presets.get(currentPreset)
+    if (presetDescription && config) {
+      presetDescription.textContent = describePreset(config)
     }
   }
 
@@ -1243,7 +1249,7 @@ setupPreviewSlider()
 setupTheme()
 updateStats()
 
-worker.postMessage({ type: 'ping' })
+worker.postMessage({ type: 'ping', cancelFlag })
 
 window.addEventListener('beforeunload', () => {
   for (const output of state.outputs.values()) {
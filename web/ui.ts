@@ -10,6 +10,8 @@ export interface ItemState {
   outputSize?: number;
   outputFormat?: string;
   error?: string;
+  progress?: number; // 当前阶段的完成比例 0..1
+  iteration?: number; // 目标大小搜索的轮次
 }
 
 export const elements = {
@@ -22,6 +24,7 @@ export const elements = {
   convertWebpInput: document.querySelector('#convertWebpInput') as HTMLInputElement,
   convertAvifInput: document.querySelector('#convertAvifInput') as HTMLInputElement,
   pngTruecolorInput: document.querySelector('#pngTruecolorInput') as HTMLInputElement,
+  cleanAlphaInput: document.querySelector('#cleanAlphaInput') as HTMLInputElement,
   autoRotateInput: document.querySelector('#autoRotateInput') as HTMLInputElement,
   stripExifInput: document.querySelector('#stripExifInput') as HTMLInputElement,
   targetSizeInput: document.querySelector('#targetSizeInput') as HTMLInputElement,
@@ -131,6 +134,10 @@ export function updateRow(row: HTMLElement, item: ItemState) {
   };
   if (status) {
     status.textContent = statusMap[item.status] || item.status.toUpperCase();
+    if (item.status === 'processing' && item.progress !== undefined) {
+      status.textContent += ` ${Math.round(item.progress * 100)}%`;
+      if (item.iteration) status.textContent += ` #${item.iteration}`;
+    }
     status.className = `file-status ${item.status}`;
   }
 
@@ -177,6 +184,10 @@ export function getMimeTypeFromFileName(fileName: string): string {
     jpeg: 'image/jpeg',
     webp: 'image/webp',
     avif: 'image/avif',
+    gif: 'image/gif',
+    jxl: 'image/jxl',
+    ico: 'image/x-icon',
+    svg: 'image/svg+xml',
   };
   return mimeTypes[ext || ''] || 'image/png';
 }
@@ -1,11 +1,31 @@
 /// <reference lib="webworker" />
-import init, {
-  compress_image,
-  detect_format,
-  get_version,
-} from './pkg/tinylocal.js'
+import init, * as singleThreaded from './pkg/tinylocal.js'
 import wasmUrl from './pkg/tinylocal_bg.wasm?url'
 
+type WasmModule = typeof singleThreaded
+type ThreadedModule = WasmModule & {
+  initThreadPool(threads: number): Promise<void>
+}
+
+// 多线程构建（WASM_THREADS=1 时生成）依赖 SharedArrayBuffer，页面跨源隔离时才加载，否则使用单线程构建
+const threadedBuild = import.meta.glob<ThreadedModule>('./pkg-threads/tinylocal.js')
+const threadedWasmUrl = import.meta.glob<string>('./pkg-threads/tinylocal_bg.wasm', {
+  query: '?url',
+  import: 'default',
+  eager: true,
+})
+
+let wasm: WasmModule = singleThreaded
+
+// 只支持解码的输入格式：照片类默认输出 JPEG，图标和截图默认输出 PNG
+// JXL 编码依赖 libjxl，wasm 构建只能解码
+const inputOnlyDefaults: Record<string, string> = {
+  tiff: 'jpeg',
+  jxl: 'jpeg',
+  bmp: 'png',
+  ico: 'png',
+}
+
 type QueueItem = {
   id: string
   name: string
@@ -16,31 +36,92 @@ type QueueItem = {
   convertToWebp: boolean
   convertToAvif: boolean
   pngTruecolor: boolean
+  cleanAlpha: boolean // 填充透明像素的颜色并去掉不需要的 alpha 通道
   autoRotate: boolean
   stripExif: boolean
   targetSize?: number // 目标文件大小（字节）
   resizeMode?: string // 尺寸调整模式
   resizeValue?: number // 尺寸调整值
+  preset?: string // Rust 预设名，设置后忽略上面的压缩选项
+}
+
+type Preset = {
+  name: string
+  format: string
+  quality: number
+  options: Record<string, unknown>
 }
 
 type WorkerRequest =
   | { type: 'enqueue'; items: QueueItem[] }
   | { type: 'abort' }
-  | { type: 'ping' }
+  | { type: 'ping'; cancelFlag?: Int32Array }
   | { type: 'skip'; id: string } // 新增跳过请求
 
 let initialized = false
 let processing = false
 let abortRequested = false
 const skippedIds = new Set<string>() // 跟踪被跳过的文件ID
+// 主线程置 1 后，压缩在 Rust 的下一个检查点中止（需要跨源隔离才有 SharedArrayBuffer）
+let cancelFlag: Int32Array | undefined
 
 const queue: QueueItem[] = []
+const presets = new Map<string, Preset>()
+
+async function loadThreaded(): Promise<boolean> {
+  const load = threadedBuild['./pkg-threads/tinylocal.js']
+  const url = threadedWasmUrl['./pkg-threads/tinylocal_bg.wasm']
+  if (!self.crossOriginIsolated || !load || !url) return false
+
+  try {
+    const threaded = await load()
+    await threaded.default(url)
+    await threaded.initThreadPool(navigator.hardwareConcurrency || 4)
+    wasm = threaded
+    return true
+  } catch (error) {
+    console.warn('Threaded wasm unavailable, using single-threaded build', error)
+    return false
+  }
+}
 
 async function ensureInit() {
   if (initialized) return
-  await init(wasmUrl)
+  if (!(await loadThreaded())) {
+    await init(wasmUrl)
+  }
   initialized = true
-  self.postMessage({ type: 'ready', version: get_version() })
+  const list = wasm.list_presets() as Preset[]
+  for (const preset of list) {
+    presets.set(preset.name, preset)
+  }
+  self.postMessage({ type: 'ready', version: wasm.get_version(), presets: list })
+}
+
+// 压缩结果与实际输出格式，auto 时为 Rust 选中的格式
+type Compressed = { output: Uint8Array; format: string }
+
+// 调用 Rust 压缩并转发进度，同一阶段的进度至少间隔 5% 才发送
+function compressImage(
+  id: string,
+  data: Uint8Array,
+  format: string,
+  quality: number,
+  options: Record<string, unknown>,
+  iteration?: number
+): Compressed {
+  let lastStage = ''
+  let lastFraction = 0
+  const onProgress = (stage: string, fraction: number) => {
+    if (stage === lastStage && fraction < 1 && fraction - lastFraction < 0.05) return
+    lastStage = stage
+    lastFraction = fraction
+    self.postMessage({ type: 'progress', id, stage, fraction, iteration })
+  }
+  const result = wasm.compress_image_with_progress(data, format, quality, options, onProgress, cancelFlag)
+  const compressed = { output: result.data, format: result.format }
+  result.free()
+  return compressed
 }
 
 async function processQueue() {
@@ -50,6 +131,7 @@ async function processQueue() {
   while (queue.length > 0) {
     if (abortRequested) {
       abortRequested = false
+      resetCancelFlag()
       queue.length = 0
       self.postMessage({ type: 'aborted' })
       break
@@ -65,68 +147,96 @@ async function processQueue() {
       continue
     }
 
-    const data = new Uint8Array(job.data)
+    // 上一个任务结束后残留的取消标记不影响新任务
+    resetCancelFlag()
+
+    let data = new Uint8Array(job.data)
 
     try {
-      const detected = detect_format(data)
+      const detected = wasm.detect_format(data)
       if (detected === 'unknown') {
         throw new Error('Unsupported image format')
       }
+      // libheif 无法编译到 wasm，heif feature 只在原生构建中可用
+      if (detected === 'heic') {
+        throw new Error('HEIC/HEIF is not supported in the browser; use the native build')
+      }
+      // rav1d 无法编译到 wasm，AVIF 输入先由浏览器解码为无损 PNG 再交给 wasm
+      if (detected === 'avif') {
+        data = await decodeAvifInput(data)
+      }
 
-      let outputFormat = detected
+      // 只能作为输入的格式转换为默认输出格式
+      let outputFormat = inputOnlyDefaults[detected] ?? detected
+      // 不支持动画 AVIF，动图不转换为 AVIF
       if (
         job.convertToAvif &&
-        (detected === 'png' || detected === 'jpeg' || detected === 'webp')
+        (detected === 'png' || detected === 'jpeg' || detected === 'webp') &&
+        !wasm.is_animated(data)
       ) {
         outputFormat = 'avif'
-      } else if (job.convertToWebp && detected === 'png') {
+      } else if (
+        job.convertToWebp &&
+        (detected === 'png' || detected === 'gif')
+      ) {
         outputFormat = 'webp'
       }
 
+      let quality = job.quality
+      let options: Record<string, unknown> = {
+        dithering: job.dithering,
+        progressive: job.progressive,
+        resize_mode: job.resizeMode || 'none',
+        resize_value: job.resizeValue || 100,
+        // Rust 默认不放大；界面的固定宽度模式要求输出正好是指定宽度
+        allow_upscale: job.resizeMode === 'fixed',
+        png_truecolor: job.pngTruecolor,
+        alpha_cleanup: job.cleanAlpha ? 'bleed' : 'none',
+        auto_rotate: job.autoRotate,
+        strip_exif: job.stripExif,
+      }
+      // 预设决定输出格式、质量与全部选项，与 compress_preset 的结果一致
+      const preset = job.preset ? presets.get(job.preset) : undefined
+      if (preset) {
+        outputFormat = preset.format
+        quality = preset.quality
+        options = preset.options
+      }
+
       self.postMessage({ type: 'started', id: job.id })
 
       let output: Uint8Array
-      let finalQuality = job.quality
+      let finalQuality = quality
 
       // 如果设置了目标大小，使用二分查找找到最佳质量
       if (job.targetSize) {
         const result = await findQualityForTargetSize(
+          job.id,
           data,
           outputFormat,
           job.targetSize,
-          job.dithering,
-          job.progressive,
-          job.resizeMode,
-          job.resizeValue,
-          job.pngTruecolor,
-          job.autoRotate,
-          job.stripExif
+          options
         )
         output = result.output
+        outputFormat = result.format
         finalQuality = result.quality
       } else {
         try {
-          output = compress_image(data, outputFormat, job.quality, {
-            dithering: job.dithering,
-            progressive: job.progressive,
-            resize_mode: job.resizeMode || 'none',
-            resize_value: job.resizeValue || 100,
-            png_truecolor: job.pngTruecolor,
-            auto_rotate: job.autoRotate,
-            strip_exif: job.stripExif,
-          })
+          const result = compressImage(job.id, data, outputFormat, quality, options)
+          output = result.output
+          outputFormat = result.format
         } catch (error) {
           const message = error instanceof Error ? error.message : String(error)
           if (
             outputFormat === 'webp' &&
             message.includes('WebP feature not enabled')
           ) {
-            output = await encodeWebpFallback(data, job.quality)
+            output = await encodeWebpFallback(data, quality)
           } else if (
             outputFormat === 'avif' &&
             message.includes('AVIF encoding requires')
           ) {
-            output = await encodeAvifFallback(data, job.quality)
+            output = await encodeAvifFallback(data, quality)
           } else {
             throw error
           }
@@ -145,7 +255,14 @@ async function processQueue() {
       )
     } catch (error) {
       const message = error instanceof Error ? error.message : String(error)
-      self.postMessage({ type: 'error', id: job.id, message })
+      if (message === 'Cancelled') {
+        self.postMessage({ type: 'cancelled', id: job.id })
+      } else {
+        self.postMessage({ type: 'error', id: job.id, message })
+      }
+    } finally {
+      // 池中的缓冲区按上一张图的大小分配，任务之间不再保留
+      wasm.release_buffers()
     }
   }
 
@@ -154,39 +271,29 @@ async function processQueue() {
 
 // 使用二分查找找到符合目标大小的最佳质量
 async function findQualityForTargetSize(
+  id: string,
   data: Uint8Array,
   format: string,
   targetSize: number,
-  dithering: boolean,
-  progressive: boolean,
-  resizeMode?: string,
-  resizeValue?: number,
-  pngTruecolor?: boolean,
-  autoRotate?: boolean,
-  stripExif?: boolean
-): Promise<{ output: Uint8Array; quality: number }> {
+  options: Record<string, unknown>
+): Promise<Compressed & { quality: number }> {
   const minQuality = 40
   const maxQuality = 100
   const tolerance = 0.05 // 允许5%的误差
 
-  const compress = async (quality: number): Promise<Uint8Array> => {
+  // 每次尝试计为一轮迭代，进度消息带上轮次
+  let iteration = 0
+  const compress = async (quality: number): Promise<Compressed> => {
+    iteration += 1
     try {
-      return compress_image(data, format, quality, {
-        dithering,
-        progressive,
-        resize_mode: resizeMode || 'none',
-        resize_value: resizeValue || 100,
-        png_truecolor: pngTruecolor,
-        auto_rotate: autoRotate,
-        strip_exif: stripExif,
-      })
+      return compressImage(id, data, format, quality, options, iteration)
     } catch (error) {
       const message = error instanceof Error ? error.message : String(error)
       if (format === 'webp' && message.includes('WebP feature not enabled')) {
-        return await encodeWebpFallback(data, quality)
+        return { output: await encodeWebpFallback(data, quality), format }
       }
       if (format === 'avif' && message.includes('AVIF encoding requires')) {
-        return await encodeAvifFallback(data, quality)
+        return { output: await encodeAvifFallback(data, quality), format }
       }
       throw error
     }
@@ -195,20 +302,20 @@ async function findQualityForTargetSize(
   // 二分查找
   let low = minQuality
   let high = maxQuality
-  let bestOutput: Uint8Array | null = null
+  let bestOutput: Compressed | null = null
   let bestQuality = minQuality
 
   // 先检查最大质量是否已经满足目标大小
   const maxOutput = await compress(maxQuality)
-  if (maxOutput.length <= targetSize * (1 + tolerance)) {
-    return { output: maxOutput, quality: maxQuality }
+  if (maxOutput.output.length <= targetSize * (1 + tolerance)) {
+    return { ...maxOutput, quality: maxQuality }
   }
 
   // 检查最小质量是否仍然太大
   const minOutput = await compress(minQuality)
-  if (minOutput.length > targetSize) {
+  if (minOutput.output.length > targetSize) {
     // 无法达到目标大小，返回最小质量的结果
-    return { output: minOutput, quality: minQuality }
+    return { ...minOutput, quality: minQuality }
   }
 
   // 二分查找最佳质量
@@ -216,10 +323,10 @@ async function findQualityForTargetSize(
     const mid = Math.floor((low + high) / 2)
     const output = await compress(mid)
 
-    if (output.length > targetSize * (1 + tolerance)) {
+    if (output.output.length > targetSize * (1 + tolerance)) {
       // 文件太大，需要更低的质量
       high = mid
-    } else if (output.length < targetSize * (1 - tolerance)) {
+    } else if (output.output.length < targetSize * (1 - tolerance)) {
       // 文件太小，可以尝试更高质量
       low = mid
       bestOutput = output
@@ -236,21 +343,25 @@ async function findQualityForTargetSize(
   const lowOutput = await compress(low)
   const highOutput = await compress(high)
 
-  const lowDiff = Math.abs(lowOutput.length - targetSize)
-  const highDiff = Math.abs(highOutput.length - targetSize)
+  const lowDiff = Math.abs(lowOutput.output.length - targetSize)
+  const highDiff = Math.abs(highOutput.output.length - targetSize)
   const bestDiff = bestOutput
-    ? Math.abs(bestOutput.length - targetSize)
+    ? Math.abs(bestOutput.output.length - targetSize)
     : Infinity
 
   if (lowDiff <= highDiff && lowDiff <= bestDiff) {
-    return { output: lowOutput, quality: low }
+    return { ...lowOutput, quality: low }
   } else if (highDiff <= bestDiff) {
-    return { output: highOutput, quality: high }
+    return { ...highOutput, quality: high }
   } else if (bestOutput) {
-    return { output: bestOutput, quality: bestQuality }
+    return { ...bestOutput, quality: bestQuality }
   }
 
-  return { output: highOutput, quality: high }
+  return { ...highOutput, quality: high }
+}
+
+function resetCancelFlag() {
+  if (cancelFlag) Atomics.store(cancelFlag, 0, 0)
 }
 
 async function encodeWebpFallback(
@@ -296,6 +407,33 @@ async function encodeWebpFallback(
   }
 }
 
+async function decodeAvifInput(data: Uint8Array): Promise<Uint8Array> {
+  if (
+    !('OffscreenCanvas' in self) ||
+    typeof createImageBitmap === 'undefined'
+  ) {
+    throw new Error('AVIF input is not supported in this environment')
+  }
+
+  let bitmap: ImageBitmap
+  try {
+    bitmap = await createImageBitmap(
+      new Blob([data.buffer as ArrayBuffer], { type: 'image/avif' })
+    )
+  } catch {
+    throw new Error('AVIF input is not supported in this browser')
+  }
+  const canvas = new OffscreenCanvas(bitmap.width, bitmap.height)
+  const ctx = canvas.getContext('2d')
+  if (!ctx) {
+    throw new Error('AVIF decoding unavailable (no 2D context)')
+  }
+  ctx.drawImage(bitmap, 0, 0)
+  bitmap.close()
+  const outBlob = await canvas.convertToBlob({ type: 'image/png' })
+  return new Uint8Array(await outBlob.arrayBuffer())
+}
+
 async function encodeAvifFallback(
   data: Uint8Array,
   quality: number
@@ -338,13 +476,18 @@ self.onmessage = async (event: MessageEvent<WorkerRequest>) => {
   const message = event.data
 
   if (message.type === 'ping') {
+    cancelFlag = message.cancelFlag
     await ensureInit()
     return
   }
 
   if (message.type === 'abort') {
     abortRequested = true
+    // 标记可能已在任务开始时被清除，这里重新置位以取消正在运行的任务
+    if (cancelFlag) Atomics.store(cancelFlag, 0, 1)
     if (!processing) {
+      abortRequested = false
+      resetCancelFlag()
       queue.length = 0
       self.postMessage({ type: 'aborted' })
     }